@@ -3,7 +3,8 @@ use axum::{
     http::header::{HeaderMap, HeaderValue, CONTENT_TYPE},
     response::{IntoResponse, Redirect, Response},
 };
-use pozk_db::MainController;
+use chrono::Utc;
+use pozk_db::{MainController, ZeroGasWallet};
 use serde_json::{json, Value};
 use tokio::fs::read;
 
@@ -23,19 +24,39 @@ pub async fn login(
 }
 
 pub async fn health(Extension(app): Extension<AppContext>) -> Result<Json<Value>> {
-    let controller = app
-        .db
-        .get::<MainController>(MainController::to_key())?
-        .map(|c| format!("{:?}", c.controller));
+    let main = app.db.get::<MainController>(MainController::to_key())?;
+    let controller = main.as_ref().map(|c| format!("{:?}", c.controller));
+    let zero_gas = match main {
+        Some(c) => app
+            .db
+            .get::<ZeroGasWallet>(ZeroGasWallet::to_key(&c.controller))?,
+        None => None,
+    };
 
     let (provers, _) = list_provers(&app.docker, &app.db).await?;
 
+    let now = Utc::now().timestamp() as u64;
+    let epoch = app.state.epoch();
+
     Ok(Json(json!({
         "miner": format!("{:?}", app.miner),
         "controller": controller,
         "version": PROXY_VERSION,
         "url": app.url,
-        "provers": provers
+        "provers": provers,
+        "slots": {
+            "capacity": app.slots.capacity(),
+            "used": app.slots.used(),
+        },
+        "load": app.load.info(),
+        "zero_gas": zero_gas,
+        "epoch": {
+            "current": epoch.current(now),
+            "start_time": epoch.start_time,
+            "period": epoch.period,
+            "next_in": epoch.next_in(now),
+            "maintenance": epoch.maintenance,
+        },
     })))
 }
 
@@ -0,0 +1,66 @@
+use axum::extract::{Extension, Json, Path, Query};
+use ethers::prelude::Address;
+use pozk_db::{Benchmark, Prover};
+use pozk_utils::ServiceMessage;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::app::{success, AppContext, Error, Pagination, Result};
+
+/// list the benchmarks of provers on hosts
+pub async fn index(
+    Extension(app): Extension<AppContext>,
+    Query(pagination): Query<Pagination>,
+) -> Result<Json<Value>> {
+    let (begin, take_count) = pagination.begin_and_take();
+
+    let (data, total) = app.db.list::<Benchmark>(begin, take_count)?;
+    Ok(Json(json!({
+        "data": data,
+        "total": total,
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct CreateForm {
+    /// hex of fixture inputs, use the sample of past tasks if missing
+    inputs: Option<String>,
+    publics: Option<String>,
+}
+
+/// run the prover with sample inputs, the result saved when proof uploaded
+pub async fn create(
+    Extension(app): Extension<AppContext>,
+    Path(prover): Path<String>,
+    form: Option<Json<CreateForm>>,
+) -> Result<Json<Value>> {
+    let prover: Address = prover
+        .parse()
+        .map_err(|_| Error::Invalid(1102, "Invalid address".to_owned()))?;
+
+    let key = Prover::to_key(&prover);
+    if app.db.get::<Prover>(key)?.is_none() {
+        return Err(Error::Invalid(1103, "Invalid address".to_owned()));
+    }
+
+    let fixture = match form {
+        Some(Json(CreateForm {
+            inputs: Some(inputs),
+            publics,
+        })) => {
+            let publics = publics.unwrap_or_default();
+            let inputs = hex::decode(inputs.trim_start_matches("0x"))
+                .map_err(|_| Error::Invalid(1106, "Invalid inputs".to_owned()))?;
+            let publics = hex::decode(publics.trim_start_matches("0x"))
+                .map_err(|_| Error::Invalid(1106, "Invalid publics".to_owned()))?;
+            Some((inputs, publics))
+        }
+        _ => None,
+    };
+
+    app.sender
+        .send(ServiceMessage::Benchmark(prover, fixture))
+        .expect("Service sender invalid");
+
+    Ok(success())
+}
@@ -0,0 +1,69 @@
+use axum::extract::ws::{Message, WebSocket};
+use axum::{
+    extract::{Extension, Json, Path, WebSocketUpgrade},
+    response::Response,
+};
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::app::{AppContext, Error, Result};
+use crate::jobs::{Job, JobStatus};
+
+/// list the background jobs in memory
+pub async fn index(Extension(app): Extension<AppContext>) -> Result<Json<Value>> {
+    let data = app.jobs.list();
+    Ok(Json(json!({
+        "total": data.len(),
+        "data": data,
+    })))
+}
+
+/// show the status and progress of a job
+pub async fn show(
+    Extension(app): Extension<AppContext>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>> {
+    let job = app.jobs.get(&id).ok_or(Error::NotFound(2013))?;
+    Ok(Json(job))
+}
+
+/// subscribe the progress of a job until it finished
+pub async fn progress(
+    Extension(app): Extension<AppContext>,
+    Path(id): Path<String>,
+    ws: WebSocketUpgrade,
+) -> Result<Response> {
+    let job = app.jobs.get(&id).ok_or(Error::NotFound(2013))?;
+    Ok(ws.on_upgrade(move |socket| handle_progress(socket, app, job)))
+}
+
+async fn handle_progress(mut socket: WebSocket, app: AppContext, job: Job) {
+    // subscribe first, the updates after it will not lost
+    let mut receiver = app.jobs.subscribe();
+    let id = job.id.clone();
+    let mut last = app.jobs.get(&id).unwrap_or(job);
+
+    loop {
+        let text = serde_json::to_string(&last).unwrap_or_default();
+        if socket.send(Message::Text(text)).await.is_err() {
+            return;
+        }
+        if last.status != JobStatus::Running {
+            break;
+        }
+
+        last = loop {
+            match receiver.recv().await {
+                Ok(job) if job.id == id => break job,
+                Ok(_) => continue,
+                Err(RecvError::Lagged(_)) => match app.jobs.get(&id) {
+                    Some(job) => break job,
+                    None => return,
+                },
+                Err(RecvError::Closed) => return,
+            }
+        };
+    }
+
+    let _ = socket.close().await;
+}
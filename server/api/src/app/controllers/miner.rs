@@ -0,0 +1,48 @@
+use axum::extract::{Extension, Json};
+use chrono::Utc;
+use pozk_db::MinerStatus;
+use serde_json::{json, Value};
+
+use crate::app::{AppContext, Result};
+
+/// the running status of miner
+pub async fn status(Extension(app): Extension<AppContext>) -> Result<Json<Value>> {
+    Ok(Json(json!({
+        "accepting": app.state.accepting(),
+        "paused": app.state.is_paused(),
+        "draining": app.state.is_draining(),
+        "maintenance": app.state.in_maintenance(),
+    })))
+}
+
+/// stop accepting new on-chain and API tasks, current tasks keep running
+pub async fn pause(Extension(app): Extension<AppContext>) -> Result<Json<Value>> {
+    set_paused(&app, true)?;
+    status(Extension(app)).await
+}
+
+/// accept new tasks again
+pub async fn resume(Extension(app): Extension<AppContext>) -> Result<Json<Value>> {
+    set_paused(&app, false)?;
+    status(Extension(app)).await
+}
+
+fn set_paused(app: &AppContext, paused: bool) -> Result<()> {
+    app.db.add(&MinerStatus {
+        paused,
+        updated: Utc::now().timestamp(),
+    })?;
+    app.state.set_paused(paused);
+    info!("[Api] miner paused: {}", paused);
+    Ok(())
+}
+
+/// stop accepting new tasks, and exit when current work done
+pub async fn drain(Extension(app): Extension<AppContext>) -> Result<Json<Value>> {
+    if !app.state.is_draining() {
+        info!("[Api] drain requested");
+        app.state.request_drain();
+    }
+
+    status(Extension(app)).await
+}
@@ -0,0 +1,59 @@
+use axum::extract::{Extension, Json, Path, Query};
+use pozk_db::{MinerTest, MinerTestStatus, Tx};
+use serde_json::{json, Value};
+
+use crate::app::{AppContext, Error, Pagination, Result};
+
+/// list the miner tests, newest first, and the required tests waiting to be done
+pub async fn index(
+    Extension(app): Extension<AppContext>,
+    Query(pagination): Query<Pagination>,
+) -> Result<Json<Value>> {
+    let (begin, take_count) = pagination.begin_and_take();
+
+    let total = app.db.count::<MinerTest>()?;
+    let (all, _) = app.db.list::<MinerTest>(0, total)?;
+    let required: Vec<&MinerTest> = all
+        .iter()
+        .filter(|t| {
+            matches!(
+                t.status,
+                MinerTestStatus::Required | MinerTestStatus::Running
+            )
+        })
+        .collect();
+    let data: Vec<&MinerTest> = all.iter().rev().skip(begin).take(take_count).collect();
+
+    Ok(Json(json!({
+        "required": required,
+        "data": data,
+        "total": total,
+    })))
+}
+
+/// show a miner test with its submit tx
+pub async fn show(
+    Extension(app): Extension<AppContext>,
+    Path(id): Path<u64>,
+) -> Result<Json<Value>> {
+    let test = app
+        .db
+        .get::<MinerTest>(&MinerTest::to_key(id))?
+        .ok_or(Error::NotFound(2011))?;
+
+    let tx = match test.tx {
+        Some(tx) => app.db.get::<Tx>(&Tx::to_key(tx))?.map(|tx| {
+            json!({
+                "status": tx.status,
+                "hash": tx.hash,
+                "error": tx.error,
+            })
+        }),
+        None => None,
+    };
+
+    Ok(Json(json!({
+        "test": test,
+        "tx": tx,
+    })))
+}
@@ -1,5 +1,11 @@
 pub mod auth;
+pub mod benchmark;
 pub mod connect;
 pub mod controller;
+pub mod job;
+pub mod miner;
+pub mod miner_test;
 pub mod prover;
+pub mod reward;
+pub mod staking;
 pub mod task;
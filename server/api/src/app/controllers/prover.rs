@@ -1,11 +1,13 @@
 use axum::extract::{Extension, Json, Path, Query};
+use chrono::prelude::*;
 use ethers::prelude::Address;
 use pozk_db::Prover;
-use pozk_utils::{ProverType, ServiceMessage};
+use pozk_utils::{ProverType, PullRequest, ServiceMessage};
 use serde::Deserialize;
 use serde_json::{json, Value};
 
 use crate::app::{success, AppContext, Error, Pagination, Result};
+use crate::images::rollback_prover;
 
 /// list all provers in local
 pub async fn index(
@@ -29,9 +31,15 @@ pub struct CreateForm {
     overtime: u64,
     ptype: u8,
     types: String,
+    /// the expected image digest, pinned at first pull if missing
+    digest: Option<String>,
+    /// the registry of image, e.g. registry.example.com
+    registry: Option<String>,
+    /// the organisation of image in registry
+    org: Option<String>,
 }
 
-/// create & pull & running a prover
+/// create & pull & running a prover, the pull job runs in background
 pub async fn create(
     Extension(app): Extension<AppContext>,
     Json(form): Json<CreateForm>,
@@ -41,18 +49,27 @@ pub async fn create(
         .parse()
         .map_err(|_| Error::Invalid(1102, "Invalid address".to_owned()))?;
 
+    let image = format!("{}:{}", form.name, form.tag);
+    let job = app.jobs.create("pull", prover, image);
+    let req = PullRequest {
+        prover,
+        tag: form.tag,
+        name: form.name,
+        overtime: form.overtime,
+        ptype: ProverType::from_byte(form.ptype),
+        types: form.types,
+        digest: form.digest,
+        registry: form.registry,
+        org: form.org,
+    };
     app.sender
-        .send(ServiceMessage::PullProver(
-            prover,
-            form.tag,
-            form.name,
-            form.overtime,
-            ProverType::from_byte(form.ptype),
-            form.types,
-        ))
+        .send(ServiceMessage::PullProver(req, job.clone()))
         .expect("Service sender invalid");
 
-    Ok(success())
+    Ok(Json(json!({
+        "code": 0,
+        "job": job,
+    })))
 }
 
 /// show a prover detail
@@ -72,6 +89,79 @@ pub async fn show(
     Ok(Json(p))
 }
 
+#[derive(Deserialize)]
+pub struct UpdateForm {
+    priority: Option<i64>,
+    /// max parallel tasks, 0 is unlimited
+    parallel: Option<usize>,
+    /// slots cost of one task, 0 is default
+    weight: Option<usize>,
+    /// pin the image digest, and unblock the prover
+    digest: Option<String>,
+}
+
+/// update the local settings of a prover
+pub async fn update(
+    Extension(app): Extension<AppContext>,
+    Path(prover): Path<String>,
+    Json(form): Json<UpdateForm>,
+) -> Result<Json<Prover>> {
+    let prover: Address = prover
+        .parse()
+        .map_err(|_| Error::Invalid(1102, "Invalid address".to_owned()))?;
+
+    let key = Prover::to_key(&prover);
+    let mut p = app
+        .db
+        .get::<Prover>(key)?
+        .ok_or(Error::Invalid(1103, "Invalid address".to_owned()))?;
+
+    if let Some(priority) = form.priority {
+        p.priority = priority;
+    }
+    if let Some(parallel) = form.parallel {
+        p.parallel = if parallel == 0 { None } else { Some(parallel) };
+    }
+    if let Some(weight) = form.weight {
+        p.weight = if weight == 0 { None } else { Some(weight) };
+    }
+    if let Some(digest) = form.digest {
+        let digest = digest.trim().trim_start_matches("sha256:").to_lowercase();
+        p.digest = if digest.is_empty() {
+            None
+        } else {
+            Some(digest)
+        };
+        p.blocked = None;
+    }
+    app.db.add(&p)?;
+
+    Ok(Json(p))
+}
+
+/// swap back to the previous version of upgraded prover
+pub async fn rollback(
+    Extension(app): Extension<AppContext>,
+    Path(prover): Path<String>,
+) -> Result<Json<Prover>> {
+    let prover: Address = prover
+        .parse()
+        .map_err(|_| Error::Invalid(1102, "Invalid address".to_owned()))?;
+
+    let key = Prover::to_key(&prover);
+    let p = app
+        .db
+        .get::<Prover>(key)?
+        .ok_or(Error::Invalid(1103, "Invalid address".to_owned()))?;
+    if p.previous.is_none() {
+        return Err(Error::Invalid(1107, "No previous version".to_owned()));
+    }
+
+    let until = Utc::now().timestamp() + app.upgrade_grace;
+    let p = rollback_prover(&app.db, &prover, until)?;
+    Ok(Json(p))
+}
+
 /// delete a prover from local
 pub async fn delete(
     Extension(app): Extension<AppContext>,
@@ -88,27 +178,44 @@ pub async fn delete(
     Ok(success())
 }
 
+/// pull the missing images of provers in background jobs
 pub async fn repair(Extension(app): Extension<AppContext>) -> Result<Json<Value>> {
     let count = app.db.count::<Prover>()?;
     let (data, total) = app.db.list::<Prover>(0, count)?;
     let images = app.docker.list().await?;
 
     // check docker image exists
-    let mut list = vec![];
-    for mut p in data {
+    let mut jobs = vec![];
+    for p in data.iter() {
         if images.contains_key(&p.image) {
-            list.push(p);
             continue;
         }
-        let image = app.docker.pull(&p.name, &p.tag).await?;
 
-        p.image = image;
-        app.db.add(&p)?;
-        list.push(p);
+        let image = format!("{}:{}", p.name, p.tag);
+        let job = app.jobs.create("pull", p.prover, image);
+        let req = PullRequest {
+            prover: p.prover,
+            tag: p.tag.clone(),
+            name: p.name.clone(),
+            overtime: p.overtime,
+            ptype: p.ptype,
+            types: p.types.clone(),
+            digest: p.digest.clone(),
+            registry: p.registry.clone(),
+            org: p.org.clone(),
+        };
+        app.sender
+            .send(ServiceMessage::PullProver(req, job.clone()))
+            .expect("Service sender invalid");
+        jobs.push(json!({
+            "prover": p.prover,
+            "job": job,
+        }));
     }
 
     Ok(Json(json!({
-        "data": list,
+        "data": data,
         "total": total,
+        "jobs": jobs,
     })))
 }
@@ -0,0 +1,44 @@
+use axum::extract::{Extension, Json, Query};
+use ethers::prelude::U256;
+use pozk_db::Reward;
+use serde_json::{json, Value};
+
+use crate::app::{AppContext, Pagination, Result};
+
+/// list the labors and collected rewards of miner by epoch
+pub async fn index(
+    Extension(app): Extension<AppContext>,
+    Query(pagination): Query<Pagination>,
+) -> Result<Json<Value>> {
+    let (begin, take_count) = pagination.begin_and_take();
+    let current = app.current_epoch().await?;
+
+    // summary of all epochs
+    let total = app.db.count::<Reward>()?;
+    let (all, _) = app.db.list::<Reward>(0, total)?;
+    let mut collected = U256::zero();
+    let mut claimable = U256::zero();
+    let mut epochs = vec![];
+    for r in all.iter() {
+        match r.amount {
+            Some(amount) => collected += amount,
+            None if r.epoch < current => {
+                claimable += r.work;
+                if !epochs.contains(&r.epoch) {
+                    epochs.push(r.epoch);
+                }
+            }
+            None => {}
+        }
+    }
+
+    let data: Vec<&Reward> = all.iter().rev().skip(begin).take(take_count).collect();
+    Ok(Json(json!({
+        "epoch": current,
+        "collected": collected,
+        "claimable_work": claimable,
+        "claimable_epochs": epochs,
+        "data": data,
+        "total": total,
+    })))
+}
@@ -0,0 +1,131 @@
+use axum::extract::{Extension, Json};
+use ethers::prelude::{Address, Bytes, U256};
+use futures_util::future::{try_join, try_join_all};
+use pozk_db::{Prover, Unstaking};
+use pozk_utils::ServiceMessage;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::app::{success, AppContext, Error, Result};
+
+/// the staking of miner in local provers, and the unstaking queue
+pub async fn index(Extension(app): Extension<AppContext>) -> Result<Json<Value>> {
+    let current = app.current_epoch().await?;
+
+    // read the staking of all provers concurrently
+    let count = app.db.count::<Prover>()?;
+    let (provers, _) = app.db.list::<Prover>(0, count)?;
+    let staking = try_join_all(provers.iter().map(|p| async {
+        let amount_call = app.stake.miner_staking(p.prover, app.miner);
+        let total_call = app.stake.miner_total_staking(p.prover);
+        let (amount, total) = try_join(amount_call.call(), total_call.call()).await?;
+        Ok::<_, Error>(json!({
+            "prover": format!("{:?}", p.prover),
+            "staking": amount,
+            "total": total,
+        }))
+    }))
+    .await?;
+
+    // the unstaking which still locked
+    let count = app.db.count::<Unstaking>()?;
+    let (unstaking, _) = app.db.list::<Unstaking>(0, count)?;
+    let unstaking: Vec<Unstaking> = unstaking
+        .into_iter()
+        .filter(|u| u.epoch > current)
+        .collect();
+
+    let claimable = app.stake.claimable(app.miner).call().await?;
+
+    Ok(Json(json!({
+        "epoch": current,
+        "staking": staking,
+        "unstaking": unstaking,
+        "claimable": claimable,
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct StakeForm {
+    prover: String,
+    /// amount in wei
+    amount: String,
+}
+
+impl StakeForm {
+    fn parse(&self) -> Result<(Address, U256)> {
+        let prover: Address = self
+            .prover
+            .parse()
+            .map_err(|_| Error::Invalid(1102, "Invalid address".to_owned()))?;
+        let amount = U256::from_dec_str(&self.amount)
+            .ok()
+            .filter(|a| !a.is_zero())
+            .ok_or(Error::Invalid(1104, "Invalid amount".to_owned()))?;
+        Ok((prover, amount))
+    }
+}
+
+fn prepared(to: Address, data: Option<Bytes>) -> Value {
+    json!({
+        "to": format!("{:?}", to),
+        "data": data.unwrap_or_default(),
+    })
+}
+
+/// prepare the txs to stake, which must be signed by miner account
+pub async fn stake(
+    Extension(app): Extension<AppContext>,
+    Json(form): Json<StakeForm>,
+) -> Result<Json<Value>> {
+    let (prover, amount) = form.parse()?;
+    let stake = app.stake.address();
+
+    let mut txs = vec![];
+    let allowance = app.token.allowance(app.miner, stake).call().await?;
+    if allowance < amount {
+        let approve = app.token.approve(stake, amount);
+        txs.push(prepared(app.token.address(), approve.calldata()));
+    }
+    let call = app.stake.miner_stake(prover, amount);
+    txs.push(prepared(stake, call.calldata()));
+
+    Ok(Json(json!({
+        "from": format!("{:?}", app.miner),
+        "txs": txs,
+    })))
+}
+
+/// prepare the tx to unstake, which must be signed by miner account
+pub async fn unstake(
+    Extension(app): Extension<AppContext>,
+    Json(form): Json<StakeForm>,
+) -> Result<Json<Value>> {
+    let (prover, amount) = form.parse()?;
+    let staking = app.stake.miner_staking(prover, app.miner).call().await?;
+    if staking < amount {
+        return Err(Error::Invalid(1104, "Invalid amount".to_owned()));
+    }
+
+    let call = app.stake.miner_unstake(prover, amount);
+    let txs = vec![prepared(app.stake.address(), call.calldata())];
+
+    Ok(Json(json!({
+        "from": format!("{:?}", app.miner),
+        "txs": txs,
+    })))
+}
+
+/// claim the unstaked tokens to miner, sent by controller
+pub async fn claim(Extension(app): Extension<AppContext>) -> Result<Json<Value>> {
+    let claimable = app.stake.claimable(app.miner).call().await?;
+    if claimable.is_zero() {
+        return Err(Error::Invalid(1105, "Nothing to claim".to_owned()));
+    }
+
+    app.sender
+        .send(ServiceMessage::Claim)
+        .expect("Service sender invalid");
+
+    Ok(success())
+}
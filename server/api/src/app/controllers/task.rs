@@ -6,16 +6,18 @@ use chrono::Utc;
 use ethers::prelude::{Address, Signature, H160};
 use pozk_db::Prover;
 use pozk_docker::RunOption;
-use pozk_utils::{
-    check_task_proxy_list, read_task_input, read_task_proof, write_task_input, ServiceMessage,
-};
+use pozk_utils::{check_task_proxy_list, ServiceMessage};
 use rand::distributions::{Alphanumeric, DistString};
 use serde_json::{json, Value};
 
 use crate::app::{success, AppContext, Error, Result};
+use crate::images::verify_image;
 
-pub async fn download(Path(id): Path<String>) -> Result<Bytes> {
-    let data = read_task_input(&id).await?;
+pub async fn download(
+    Extension(app): Extension<AppContext>,
+    Path(id): Path<String>,
+) -> Result<Bytes> {
+    let data = app.files.read_task_input(&id).await?;
     Ok(data.into())
 }
 
@@ -50,26 +52,59 @@ pub async fn create(Extension(app): Extension<AppContext>, body: Bytes) -> Resul
 
     // 1. check prover
     let key = Prover::to_key(&task.prover);
-    let p = app
+    let mut p = app
         .db
         .get::<Prover>(key)?
         .ok_or(Error::Invalid(1103, "Invalid prover".to_owned()))?;
+    if verify_image(&app.db, app.docker.as_ref(), &mut p)
+        .await
+        .is_err()
+    {
+        return Err(Error::Invalid(2012, "Prover image not verified".to_owned()));
+    }
 
-    // 2. write data to file
+    // 2. take the slots
     let code = Alphanumeric.sample_string(&mut rand::thread_rng(), 10);
     let sid = format!("p-{}", code);
     let zkvm = app.zkvm.as_ref().map(|v| v.as_str()).unwrap_or("");
-    let over_at = Utc::now().timestamp() + p.overtime as i64;
+    let now = Utc::now().timestamp();
+    let over_at = now + p.overtime as i64;
+
+    if !app.state.accepting() {
+        return Err(Error::Invalid(
+            2010,
+            "Miner is not accepting tasks".to_owned(),
+        ));
+    }
+    if !app.load.admit() {
+        return Err(Error::Invalid(2009, "Miner host is saturated".to_owned()));
+    }
+    let (weight, parallel) = app.slots.limits(&p);
+    if !app.slots.acquire(&sid, p.prover, weight, parallel, now) {
+        return Err(Error::Invalid(2008, "Miner is busy".to_owned()));
+    }
 
-    write_task_input(&sid, task.inputs, task.publics).await?;
+    // 3. write data to file
+    if let Err(e) = app
+        .files
+        .write_task_input(&sid, task.inputs, task.publics)
+        .await
+    {
+        app.slots.release(&sid);
+        return Err(e.into());
+    }
 
-    // 3. start docker container to run, TODO we can do more about cpu & memory
-    let _container = app
+    // 4. start docker container to run, TODO we can do more about cpu & memory
+    if let Err(e) = app
         .docker
         .run(&p.image, &sid, zkvm, over_at, RunOption::default())
-        .await?;
+        .await
+    {
+        app.slots.release(&sid);
+        return Err(e.into());
+    }
 
-    // 4. create one time channel to services
+    // 5. create one time channel to services
     if app
         .sender
         .send(ServiceMessage::ApiTask(sid.clone(), over_at))
@@ -86,8 +121,11 @@ pub async fn create(Extension(app): Extension<AppContext>, body: Bytes) -> Resul
 }
 
 /// track task result from player service
-pub async fn track(Path(id): Path<String>) -> Result<Json<Value>> {
-    match read_task_proof(&id).await {
+pub async fn track(
+    Extension(app): Extension<AppContext>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>> {
+    match app.files.read_task_proof(&id).await {
         Ok(proof) => Ok(Json(json!({
             "code": 0,
             "result": hex::encode(proof),
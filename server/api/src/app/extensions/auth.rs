@@ -1,6 +1,6 @@
 use axum::{
     async_trait,
-    extract::FromRequestParts,
+    extract::{FromRequestParts, MatchedPath},
     http::{header::AUTHORIZATION, request::Parts},
     Extension,
 };
@@ -12,6 +12,9 @@ use siwe::{Message, VerificationOpts};
 
 use crate::app::{AppContext, Error, Result};
 
+/// the route of job progress, the only one accepts the token in query
+const WS_PATH: &str = "/api/jobs/:id/ws";
+
 pub struct Auth;
 
 #[derive(Serialize, Deserialize)]
@@ -93,23 +96,32 @@ where
             .await
             .map_err(|_| Error::Internal(2056))?;
 
-        // Get authorisation header
-        let authorisation = req
-            .headers
-            .get(AUTHORIZATION)
-            .ok_or(Error::Auth)?
-            .to_str()
-            .map_err(|_| Error::Auth)?;
-
-        // Check that is bearer and jwt
-        let split = authorisation.split_once(' ');
-        let jwt = match split {
-            Some((name, contents)) if name == "Bearer" => Ok(contents),
-            _ => Err(Error::Auth),
-        }?;
+        // Get authorisation header, or the token in query only for WebSocket (cannot set header)
+        let websocket = req
+            .extensions
+            .get::<MatchedPath>()
+            .map(|p| p.as_str() == WS_PATH)
+            .unwrap_or(false);
+        let query_token = req.uri.query().filter(|_| websocket).and_then(|q| {
+            q.split('&')
+                .find_map(|kv| kv.strip_prefix("token="))
+                .map(|v| v.to_owned())
+        });
+        let jwt = match req.headers.get(AUTHORIZATION) {
+            Some(authorisation) => {
+                let authorisation = authorisation.to_str().map_err(|_| Error::Auth)?;
+
+                // Check that is bearer and jwt
+                match authorisation.split_once(' ') {
+                    Some((name, contents)) if name == "Bearer" => contents.to_owned(),
+                    _ => return Err(Error::Auth),
+                }
+            }
+            None => query_token.ok_or(Error::Auth)?,
+        };
 
         let decoded = decode::<Claims>(
-            jwt,
+            &jwt,
             &DecodingKey::from_secret(&context.secret),
             &Validation::new(Algorithm::HS512),
         )
@@ -9,10 +9,13 @@ use axum::{
     middleware::from_extractor,
     routing::{get, post, Router},
 };
+use chrono::prelude::*;
 use ethers::prelude::{Address, Http, Provider};
 use pozk_db::ReDB;
-use pozk_docker::DockerManager;
-use pozk_utils::{contract_address, DefaultProvider, ServiceMessage, Task};
+use pozk_docker::ProverRuntime;
+use pozk_utils::{
+    contract_address, DefaultProvider, Epoch, ServiceMessage, Stake, Task, TaskFiles, Token,
+};
 use rand::{thread_rng, Rng};
 use serde::Deserialize;
 use serde_json::{json, Value};
@@ -27,7 +30,12 @@ use extensions::auth::Auth;
 use extensions::error::fallback;
 
 use crate::config::ApiConfig;
+use crate::context::SharedContext;
+use crate::jobs::Jobs;
+use crate::load::HostLoad;
 use crate::p2p::P2pMessage;
+use crate::slots::Slots;
+use crate::state::MinerState;
 
 pub fn success() -> Json<Value> {
     Json(json!({ "code": 0 }))
@@ -40,26 +48,30 @@ pub struct App {
     port: u16,
     domains: Vec<String>,
     db: Arc<ReDB>,
-    docker: Arc<DockerManager>,
+    files: TaskFiles,
+    docker: Arc<dyn ProverRuntime>,
+    jobs: Arc<Jobs>,
     sender: UnboundedSender<ServiceMessage>,
     p2p_sender: UnboundedSender<P2pMessage>,
     secret: [u8; 32],
     task: Task<DefaultProvider>,
+    epoch: Epoch<DefaultProvider>,
+    stake: Stake<DefaultProvider>,
+    token: Token<DefaultProvider>,
     url: String,
     zkvm: Option<String>,
+    slots: Arc<Slots>,
+    load: Arc<HostLoad>,
+    state: Arc<MinerState>,
+    upgrade_grace: i64,
 }
 
 impl App {
     pub fn new(
         cfg: &ApiConfig,
-        db: Arc<ReDB>,
-        docker: Arc<DockerManager>,
-        sender: UnboundedSender<ServiceMessage>,
-        p2p_sender: UnboundedSender<P2pMessage>,
+        ctx: &SharedContext,
         network: &str,
         endpoints: String,
-        url: String,
-        zkvm: Option<String>,
     ) -> anyhow::Result<Self> {
         let miner: Address = cfg.miner.parse()?;
         let port = cfg.http_port;
@@ -80,74 +92,125 @@ impl App {
             .split(";")
             .next()
             .ok_or(anyhow!("Invalid endpoints"))?;
-        let provider = Provider::<Http>::try_from(endpoint)?;
+        let provider = Arc::new(Provider::<Http>::try_from(endpoint)?);
         let (task_address, _) = contract_address(network, "Task")?;
-        let task = Task::new(task_address, Arc::new(provider));
+        let task = Task::new(task_address, provider.clone());
+        let (epoch_address, _) = contract_address(network, "Epoch")?;
+        let epoch = Epoch::new(epoch_address, provider.clone());
+        let (stake_address, _) = contract_address(network, "Stake")?;
+        let stake = Stake::new(stake_address, provider.clone());
+        let (token_address, _) = contract_address(network, "Token")?;
+        let token = Token::new(token_address, provider);
 
         Ok(Self {
             miner,
             port,
             domains,
-            db,
-            docker,
-            sender,
-            p2p_sender,
+            db: ctx.db.clone(),
+            files: ctx.files.clone(),
+            docker: ctx.docker.clone(),
+            jobs: ctx.jobs.clone(),
+            sender: ctx.service_sender.clone(),
+            p2p_sender: ctx.p2p_sender.clone(),
             secret,
             task,
-            url,
-            zkvm,
+            epoch,
+            stake,
+            token,
+            url: ctx.url.clone(),
+            zkvm: ctx.zkvm.clone(),
+            slots: ctx.slots.clone(),
+            load: ctx.load.clone(),
+            state: ctx.state.clone(),
+            upgrade_grace: ctx.upgrade_grace,
         })
     }
 
     pub fn run(self) {
         tokio::spawn(async move {
             let addr = SocketAddr::from(([0, 0, 0, 0], self.port));
-
-            // cors
-            let cors = CorsLayer::new()
-                .allow_methods([Method::GET, Method::POST, Method::DELETE])
-                .allow_headers(Any)
-                .allow_origin(Any);
-
-            let app = Router::new()
-                .route("/login", post(auth::login))
-                .route("/health", get(auth::health))
-                .route("/orders", post(task::create))
-                .route("/orders/:id", post(task::track))
-                .route("/connect/:id", get(connect::player))
-                .route("/repair", post(prover::repair))
-                .nest(
-                    "/inner",
-                    Router::new()
-                        .route("/tasks/:id", get(task::download).post(task::upload))
-                        .route("/connect/:id", get(connect::prover)),
-                )
-                .nest(
-                    "/api",
-                    Router::new()
-                        .route(
-                            "/controllers",
-                            get(controller::index).post(controller::create),
-                        )
-                        .route(
-                            "/controllers/:address",
-                            get(controller::show).post(controller::update),
-                        )
-                        .route("/provers", get(prover::index).post(prover::create))
-                        .route("/provers/:prover", get(prover::show).delete(prover::delete))
-                        .route_layer(from_extractor::<Auth>()),
-                )
-                .route("/", get(auth::webapp))
-                .route("/*path", get(auth::webapp))
-                .layer(Extension(Arc::new(self)))
-                .layer(cors)
-                .fallback(fallback);
+            let app = self.router();
 
             info!("* HTTP listening: {}", addr);
             let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
             axum::serve(listener, app).await.unwrap();
         });
     }
+
+    /// current epoch from the scanned state, `Epoch.get` reverts in maintenance.
+    /// read the height from chain before the first scan
+    async fn current_epoch(&self) -> Result<u64> {
+        let epoch = self.state.epoch();
+        if epoch.period > 0 {
+            return Ok(epoch.current(Utc::now().timestamp() as u64));
+        }
+        Ok(self.epoch.height().call().await?.as_u64())
+    }
+
+    /// the routes of webapp, API and inner task API
+    pub fn router(self) -> Router {
+        // cors
+        let cors = CorsLayer::new()
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
+            .allow_headers(Any)
+            .allow_origin(Any);
+
+        Router::new()
+            .route("/login", post(auth::login))
+            .route("/health", get(auth::health))
+            .route("/orders", post(task::create))
+            .route("/orders/:id", post(task::track))
+            .route("/connect/:id", get(connect::player))
+            .route("/repair", post(prover::repair))
+            .nest(
+                "/inner",
+                Router::new()
+                    .route("/tasks/:id", get(task::download).post(task::upload))
+                    .route("/connect/:id", get(connect::prover)),
+            )
+            .nest(
+                "/api",
+                Router::new()
+                    .route(
+                        "/controllers",
+                        get(controller::index).post(controller::create),
+                    )
+                    .route(
+                        "/controllers/:address",
+                        get(controller::show).post(controller::update),
+                    )
+                    .route("/provers", get(prover::index).post(prover::create))
+                    .route(
+                        "/provers/:prover",
+                        get(prover::show)
+                            .post(prover::update)
+                            .delete(prover::delete),
+                    )
+                    .route("/provers/:prover/benchmark", post(benchmark::create))
+                    .route("/provers/:prover/rollback", post(prover::rollback))
+                    .route("/benchmarks", get(benchmark::index))
+                    .route("/jobs", get(job::index))
+                    .route("/jobs/:id", get(job::show))
+                    .route("/jobs/:id/ws", get(job::progress))
+                    .route("/miner", get(miner::status))
+                    .route("/miner/pause", post(miner::pause))
+                    .route("/miner/resume", post(miner::resume))
+                    .route("/miner/drain", post(miner::drain))
+                    .route("/miner-tests", get(miner_test::index))
+                    .route("/miner-tests/:id", get(miner_test::show))
+                    .route("/rewards", get(reward::index))
+                    .route("/staking", get(staking::index))
+                    .route("/staking/stake", post(staking::stake))
+                    .route("/staking/unstake", post(staking::unstake))
+                    .route("/staking/claim", post(staking::claim))
+                    .route_layer(from_extractor::<Auth>()),
+            )
+            .route("/", get(auth::webapp))
+            .route("/*path", get(auth::webapp))
+            .layer(Extension(Arc::new(self)))
+            .layer(cors)
+            .fallback(fallback)
+    }
 }
 
 #[derive(Deserialize, Debug)]
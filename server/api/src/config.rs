@@ -1,5 +1,9 @@
 use clap::Args;
+use ethers::prelude::Address;
+use pozk_docker::{load_docker_config, registry_auth, RegistryAuths};
+use pozk_utils::ProverType;
 use serde::Deserialize;
+use std::collections::HashMap;
 
 #[derive(Args, Debug, Clone, Deserialize)]
 pub struct ApiConfig {
@@ -42,3 +46,300 @@ impl Default for ApiConfig {
         }
     }
 }
+
+#[derive(Args, Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServiceConfig {
+    #[clap(long, help = "`service`: weight of task fee when scheduling, eg. 1.0")]
+    pub fee_weight: f64,
+
+    #[clap(
+        long,
+        help = "`service`: weight of remaining overtime when scheduling, eg. 1.0"
+    )]
+    pub overtime_weight: f64,
+
+    #[clap(
+        long,
+        help = "`service`: weight of prover priority when scheduling, eg. 1.0"
+    )]
+    pub priority_weight: f64,
+
+    #[clap(
+        long,
+        help = "`service`: weight of estimated proving time when scheduling, eg. 1.0"
+    )]
+    pub duration_weight: f64,
+
+    #[clap(long, help = "`service`: slots cost of a ZK_VM task, eg. 4")]
+    pub zkvm_weight: usize,
+
+    #[clap(
+        long,
+        help = "`service`: max cpu usage (%) to accept new tasks, 0 means no limit, eg. 90"
+    )]
+    pub max_cpu_load: f32,
+
+    #[clap(
+        long,
+        help = "`service`: min available memory (MB) to accept new tasks, 0 means no limit, eg. 1024"
+    )]
+    pub min_free_memory: u64,
+
+    #[clap(
+        long,
+        help = "`service`: min available disk space (MB) to accept new tasks, 0 means no limit, eg. 2048"
+    )]
+    pub min_free_disk: u64,
+
+    #[clap(
+        long,
+        help = "`service`: max seconds a task waits in the pending queue, 0 means no limit, eg. 600"
+    )]
+    pub task_stale: i64,
+
+    #[clap(
+        long,
+        help = "`service`: max seconds waiting for running tasks when draining, eg. 600"
+    )]
+    pub drain_timeout: u64,
+
+    #[clap(
+        long,
+        help = "`service`: auto install the approved minable provers, none|all|allowlist|types, eg. all"
+    )]
+    pub auto_install: String,
+
+    #[clap(
+        long,
+        help = "`service`: provers to auto install with allowlist policy, eg. 0xabc..,0xdef.."
+    )]
+    pub auto_install_provers: String,
+
+    #[clap(
+        long,
+        help = "`service`: prover types to auto install with types policy, zk|zkvm|z4|ai_model|ai_agent, eg. zk,zkvm"
+    )]
+    pub auto_install_types: String,
+
+    #[clap(
+        long,
+        help = "`service`: seconds to keep the previous image of upgraded prover for rollback, eg. 86400"
+    )]
+    pub upgrade_grace: i64,
+
+    #[clap(
+        long,
+        help = "`service`: signed manifest of prover image digests, the automatic installs and upgrades must be pinned by it, eg. https://example.com/manifest.json"
+    )]
+    pub image_manifest: Option<String>,
+
+    #[clap(
+        long,
+        help = "`service`: the account which signs the image manifest, eg. 0x6cF0DE16160A1eF873f196aC9FB671e20598e2F8"
+    )]
+    pub manifest_signer: Option<String>,
+
+    #[clap(
+        long,
+        help = "`service`: seconds between the garbage collections of images and task files, 0 to disable, eg. 3600"
+    )]
+    pub gc_interval: i64,
+
+    #[clap(
+        long,
+        help = "`service`: seconds to keep the leftover task inputs and proofs, eg. 259200"
+    )]
+    pub file_retention: u64,
+
+    #[clap(
+        long,
+        help = "`service`: disk quota (MB) of prover images and base path, 0 means no quota, eg. 51200"
+    )]
+    pub disk_quota: u64,
+
+    #[clap(
+        long,
+        help = "`service`: alert when the disk usage reaches the percent of quota, eg. 90"
+    )]
+    pub disk_alert: u64,
+
+    /// limits of provers, prover address => limit
+    #[clap(skip)]
+    pub provers: HashMap<String, ProverLimit>,
+
+    #[clap(
+        long,
+        help = "`service`: docker config.json with registry credentials, eg. /root/.docker/config.json"
+    )]
+    pub docker_config: Option<String>,
+
+    /// credentials of private registries, registry host => login
+    #[clap(skip)]
+    pub registries: HashMap<String, RegistryLogin>,
+}
+
+/// Which approved minable provers are installed automatically
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutoInstall {
+    None,
+    All,
+    Allowlist(Vec<Address>),
+    Types(Vec<ProverType>),
+}
+
+impl AutoInstall {
+    pub fn enabled(&self) -> bool {
+        *self != AutoInstall::None
+    }
+
+    pub fn allows(&self, prover: &Address, ptype: &ProverType) -> bool {
+        match self {
+            AutoInstall::None => false,
+            AutoInstall::All => true,
+            AutoInstall::Allowlist(provers) => provers.contains(prover),
+            AutoInstall::Types(types) => types.contains(ptype),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RegistryLogin {
+    pub username: String,
+    /// password or access token
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProverLimit {
+    /// max parallel tasks of the prover
+    pub parallel: Option<usize>,
+    /// slots cost of one task
+    pub weight: Option<usize>,
+}
+
+impl ServiceConfig {
+    /// credentials of registries, from docker config.json and the registries in config
+    pub fn registry_auths(&self) -> anyhow::Result<RegistryAuths> {
+        let mut auths = match &self.docker_config {
+            Some(path) => load_docker_config(path)?,
+            None => RegistryAuths::new(),
+        };
+        for (host, login) in self.registries.iter() {
+            let (host, credentials) = registry_auth(host, &login.username, &login.password);
+            auths.insert(host, credentials);
+        }
+        Ok(auths)
+    }
+
+    pub fn auto_install(&self) -> anyhow::Result<AutoInstall> {
+        let items = |s: &str| -> Vec<String> {
+            s.split(',')
+                .map(|v| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect()
+        };
+
+        match self.auto_install.as_str() {
+            "" | "none" => Ok(AutoInstall::None),
+            "all" => Ok(AutoInstall::All),
+            "allowlist" => {
+                let mut provers = vec![];
+                for p in items(&self.auto_install_provers) {
+                    provers.push(p.parse()?);
+                }
+                Ok(AutoInstall::Allowlist(provers))
+            }
+            "types" => {
+                let mut types = vec![];
+                for t in items(&self.auto_install_types) {
+                    types.push(match t.as_str() {
+                        "zk" => ProverType::ZK,
+                        "zkvm" | "zk_vm" => ProverType::ZK_VM,
+                        "z4" => ProverType::Z4,
+                        "ai_model" => ProverType::AI_MODEL,
+                        "ai_agent" => ProverType::AI_AGENT,
+                        _ => return Err(anyhow::anyhow!("Invalid prover type: {}", t)),
+                    });
+                }
+                Ok(AutoInstall::Types(types))
+            }
+            p => Err(anyhow::anyhow!("Invalid auto install policy: {}", p)),
+        }
+    }
+
+    pub fn provers(&self) -> HashMap<Address, ProverLimit> {
+        let mut provers = HashMap::new();
+        for (k, v) in self.provers.iter() {
+            match k.parse::<Address>() {
+                Ok(prover) => {
+                    provers.insert(prover, v.clone());
+                }
+                Err(_) => warn!("Invalid prover in config: {}", k),
+            }
+        }
+        provers
+    }
+}
+
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        Self {
+            fee_weight: 1.0,
+            overtime_weight: 1.0,
+            priority_weight: 1.0,
+            duration_weight: 1.0,
+            zkvm_weight: 4,
+            max_cpu_load: 0.0,
+            min_free_memory: 0,
+            min_free_disk: 0,
+            task_stale: 0,
+            drain_timeout: 600,
+            auto_install: "none".to_owned(),
+            auto_install_provers: String::new(),
+            auto_install_types: String::new(),
+            upgrade_grace: 86400,
+            image_manifest: None,
+            manifest_signer: None,
+            gc_interval: 3600,
+            file_retention: 259200,
+            disk_quota: 0,
+            disk_alert: 90,
+            provers: HashMap::new(),
+            docker_config: None,
+            registries: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_install() {
+        let mut cfg = ServiceConfig::default();
+        let prover = Address::random();
+        assert!(!cfg.auto_install().unwrap().enabled());
+
+        cfg.auto_install = "all".to_owned();
+        assert!(cfg.auto_install().unwrap().allows(&prover, &ProverType::Z4));
+
+        cfg.auto_install = "allowlist".to_owned();
+        cfg.auto_install_provers = format!("{:?}, ", prover);
+        let policy = cfg.auto_install().unwrap();
+        assert!(policy.allows(&prover, &ProverType::ZK));
+        assert!(!policy.allows(&Address::random(), &ProverType::ZK));
+
+        cfg.auto_install = "types".to_owned();
+        cfg.auto_install_types = "zk,ZKVM".to_owned();
+        let policy = cfg.auto_install().unwrap();
+        assert!(policy.allows(&prover, &ProverType::ZK_VM));
+        assert!(!policy.allows(&prover, &ProverType::AI_MODEL));
+
+        cfg.auto_install_types = "gpu".to_owned();
+        assert!(cfg.auto_install().is_err());
+        cfg.auto_install = "some".to_owned();
+        assert!(cfg.auto_install().is_err());
+    }
+}
@@ -0,0 +1,36 @@
+use pozk_db::ReDB;
+use pozk_docker::ProverRuntime;
+use pozk_utils::{ServiceMessage, TaskFiles};
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::jobs::Jobs;
+use crate::load::HostLoad;
+use crate::p2p::P2pMessage;
+use crate::slots::Slots;
+use crate::state::MinerState;
+
+/// The handles shared by the API and main service
+#[derive(Clone)]
+pub struct SharedContext {
+    pub db: Arc<ReDB>,
+    /// the task files in base path
+    pub files: TaskFiles,
+    pub docker: Arc<dyn ProverRuntime>,
+    /// background jobs of prover images
+    pub jobs: Arc<Jobs>,
+    /// slots shared by all running tasks
+    pub slots: Arc<Slots>,
+    /// live load of host
+    pub load: Arc<HostLoad>,
+    /// runtime state of miner
+    pub state: Arc<MinerState>,
+    pub service_sender: UnboundedSender<ServiceMessage>,
+    pub p2p_sender: UnboundedSender<P2pMessage>,
+    /// the public url of miner
+    pub url: String,
+    /// the zkvm service url
+    pub zkvm: Option<String>,
+    /// seconds to keep the previous image of provers for rollback
+    pub upgrade_grace: i64,
+}
@@ -0,0 +1,214 @@
+use anyhow::Result;
+use pozk_db::{Prover, PulledImage, ReDB};
+use pozk_docker::ProverRuntime;
+use pozk_utils::TaskFiles;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::config::ServiceConfig;
+use crate::context::SharedContext;
+use crate::images::clean_previous;
+use crate::jobs::Jobs;
+use crate::load::{HostLoad, MB};
+
+/// Garbage collection of disk: the pulled images not referenced by provers, the leftover
+/// task files, and the disk quota with alerts
+pub struct DiskGc {
+    db: Arc<ReDB>,
+    files: TaskFiles,
+    docker: Arc<dyn ProverRuntime>,
+    jobs: Arc<Jobs>,
+    load: Arc<HostLoad>,
+    /// seconds to keep the task files
+    retention: u64,
+    /// disk quota (MB), 0 means no quota
+    quota: u64,
+    /// alert at the percent of quota
+    alert: u64,
+    running: AtomicBool,
+}
+
+impl DiskGc {
+    pub fn new(ctx: &SharedContext, cfg: &ServiceConfig) -> Self {
+        Self {
+            db: ctx.db.clone(),
+            files: ctx.files.clone(),
+            docker: ctx.docker.clone(),
+            jobs: ctx.jobs.clone(),
+            load: ctx.load.clone(),
+            retention: cfg.file_retention,
+            quota: cfg.disk_quota,
+            alert: cfg.disk_alert,
+            running: AtomicBool::new(false),
+        }
+    }
+
+    /// collect once, the files of working tasks are kept.
+    /// when over quota, the rollback images, all leftover files and the unfetched proofs
+    /// of API tasks are removed,
+    /// and the host refuses new tasks until the usage is under quota.
+    pub async fn run(&self, working: HashSet<String>) -> Result<()> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let res = self.check(&working).await;
+        self.running.store(false, Ordering::SeqCst);
+        res
+    }
+
+    async fn check(&self, working: &HashSet<String>) -> Result<()> {
+        let mut usage = self.collect(working, self.retention, false).await?;
+
+        if self.quota > 0 && usage > self.quota {
+            error!(
+                "[Gc] ALERT: disk usage {}MB over quota {}MB, remove rollback images and leftover files",
+                usage, self.quota
+            );
+            clean_previous(&self.db, self.docker.as_ref(), i64::MAX).await?;
+            usage = self.collect(working, 0, true).await?;
+
+            if usage > self.quota {
+                error!(
+                    "[Gc] ALERT: disk usage {}MB still over quota {}MB, refuse new tasks",
+                    usage, self.quota
+                );
+            }
+        } else if self.quota > 0 && usage * 100 >= self.quota * self.alert {
+            error!(
+                "[Gc] ALERT: disk usage {}MB reaches {}% of quota {}MB",
+                usage, self.alert, self.quota
+            );
+        }
+
+        self.load.set_disk_usage(usage);
+        Ok(())
+    }
+
+    /// remove the pulled images and files not referenced, return the disk usage (MB).
+    /// the images of provers with running jobs (install or upgrade) are kept
+    async fn collect(
+        &self,
+        working: &HashSet<String>,
+        retention: u64,
+        over_quota: bool,
+    ) -> Result<u64> {
+        let count = self.db.count::<Prover>()?;
+        let (provers, _) = self.db.list::<Prover>(0, count)?;
+        let count = self.db.count::<PulledImage>()?;
+        let (records, _) = self.db.list::<PulledImage>(0, count)?;
+
+        let mut pulled = HashSet::new();
+        let mut images = HashSet::new();
+        for r in records {
+            if self.jobs.running(&r.prover) {
+                images.insert(r.id.clone());
+            }
+            pulled.insert(r.id);
+        }
+
+        let mut files = working.clone();
+        for p in provers {
+            if let Some(previous) = p.previous {
+                pulled.insert(previous.image.clone());
+                images.insert(previous.image);
+            }
+            files.insert(format!("sample-{:?}", p.prover));
+            pulled.insert(p.image.clone());
+            images.insert(p.image);
+        }
+
+        let report = self.docker.gc(&pulled, &images).await?;
+        for id in report.images.iter() {
+            self.db.remove::<PulledImage>(PulledImage::to_key(id))?;
+        }
+        let (removed, bytes) = self
+            .files
+            .prune_task_files(retention, &files, over_quota)
+            .await?;
+        if !report.images.is_empty() || report.containers > 0 || removed > 0 {
+            info!(
+                "[Gc] removed {} images, {} containers, {} files, reclaimed {}MB",
+                report.images.len(),
+                report.containers,
+                removed,
+                (report.reclaimed + bytes) / MB
+            );
+        }
+
+        Ok((report.size + self.files.base_path_usage()) / MB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestMiner;
+    use ethers::prelude::Address;
+    use pozk_docker::RunOption;
+
+    #[tokio::test]
+    async fn test_gc_over_quota() {
+        let miner = TestMiner::start(&ServiceConfig::default()).await;
+        let ctx = &miner.ctx;
+        let image = miner.install(Address::random(), "v1").await;
+
+        // the images not pulled for provers, e.g. miner itself
+        let other = ctx
+            .docker
+            .pull(None, None, "miner", "v1", &|_| {})
+            .await
+            .unwrap();
+        // the pulled images of a removed prover, and a prover installing
+        let (stale, installing) = (Address::random(), Address::random());
+        let job = ctx
+            .jobs
+            .create("install", installing, "prover:v3".to_owned());
+        let mut pulled = vec![];
+        for (p, tag) in [(stale, "v0"), (installing, "v3")] {
+            let id = ctx
+                .docker
+                .pull(None, None, "prover", tag, &|_| {})
+                .await
+                .unwrap();
+            ctx.db
+                .add(&PulledImage {
+                    id: id.clone(),
+                    prover: p,
+                    created: 0,
+                })
+                .unwrap();
+            pulled.push(id);
+        }
+        // the stopped containers, only the ones of pulled images are removed
+        for (image, tid) in [(&other, "t-other"), (&pulled[0], "t-stale")] {
+            let c = ctx
+                .docker
+                .run(image, tid, "", 60, RunOption::default())
+                .await
+                .unwrap();
+            ctx.docker.stop(&c).await.unwrap();
+        }
+        std::fs::write(miner.path.join("proof-p-abc"), [0u8; 4]).unwrap();
+        std::fs::write(miner.path.join("filler"), vec![0u8; 2 * 1024 * 1024]).unwrap();
+
+        let cfg = ServiceConfig {
+            disk_quota: 1,
+            ..Default::default()
+        };
+        let gc = DiskGc::new(ctx, &cfg);
+        gc.run(HashSet::from(["p-abc".to_owned()])).await.unwrap();
+        assert_eq!(miner.mock.containers(), vec![format!("{}-t-other", other)]);
+        assert!(ctx.docker.digests(&image).await.is_ok());
+        assert!(ctx.docker.digests(&other).await.is_ok());
+        assert!(ctx.docker.digests(&pulled[0]).await.is_err());
+        assert!(ctx.docker.digests(&pulled[1]).await.is_ok());
+        assert!(!ctx
+            .db
+            .contains::<PulledImage>(PulledImage::to_key(&pulled[0]))
+            .unwrap());
+        // the proof of API task is removed over quota, even the task is working
+        assert!(!miner.path.join("proof-p-abc").exists());
+        ctx.jobs.finish(&job, &Ok(()));
+    }
+}
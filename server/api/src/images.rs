@@ -0,0 +1,597 @@
+use anyhow::{anyhow, Result};
+use chrono::prelude::*;
+use ethers::prelude::{Address, Signature};
+use pozk_db::{PreviousImage, Prover, PulledImage, ReDB};
+use pozk_docker::{ProverRuntime, RunOption};
+use pozk_utils::{parse_task_input, PullRequest, TaskFiles};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+
+use crate::config::ServiceConfig;
+use crate::jobs::Jobs;
+use crate::slots::Slots;
+
+/// check the smoke test every 5s
+const SMOKE_TEST_INTERVAL: u64 = 5;
+
+/// the digest matches one of the image digests, with or without the `sha256:` prefix
+pub fn match_digest(digests: &[String], expected: &str) -> bool {
+    let expected = expected.trim().trim_start_matches("sha256:").to_lowercase();
+    !expected.is_empty() && digests.iter().any(|d| d.to_lowercase() == expected)
+}
+
+#[derive(Deserialize)]
+struct ManifestEntry {
+    prover: Address,
+    tag: String,
+    digest: String,
+}
+
+#[derive(Deserialize)]
+struct ManifestFile {
+    digests: Vec<ManifestEntry>,
+    /// EIP-191 signature of the manifest message
+    signature: String,
+}
+
+/// the signed message of manifest, a `prover:tag:digest` line of each entry
+fn manifest_message(entries: &[ManifestEntry]) -> String {
+    entries
+        .iter()
+        .map(|e| format!("{:?}:{}:{}\n", e.prover, e.tag, e.digest))
+        .collect()
+}
+
+/// Signed manifest of the prover image digests, a file or url. The chain not records
+/// the image digests, so the automatic installs are pinned by it, and the upgrades too
+/// when configured.
+pub struct DigestManifest {
+    source: Option<String>,
+    signer: Option<Address>,
+}
+
+impl DigestManifest {
+    /// an invalid signer refuses all automatic installs
+    pub fn new(cfg: &ServiceConfig) -> Self {
+        let signer = cfg.manifest_signer.as_ref().and_then(|s| s.parse().ok());
+        if cfg.image_manifest.is_some() && signer.is_none() {
+            error!("[Image] invalid image manifest signer, automatic installs refused");
+        }
+
+        Self {
+            source: cfg.image_manifest.clone(),
+            signer,
+        }
+    }
+
+    /// the expected digest of prover version, read from the manifest signed by the signer
+    pub async fn digest(&self, prover: &Address, tag: &str) -> Result<String> {
+        let source = self.source.as_ref().ok_or(anyhow!("no image manifest"))?;
+        let expected = self.signer.ok_or(anyhow!("no manifest signer"))?;
+        let data = if source.starts_with("http://") || source.starts_with("https://") {
+            reqwest::get(source).await?.bytes().await?.to_vec()
+        } else {
+            tokio::fs::read(source).await?
+        };
+        let file: ManifestFile = serde_json::from_slice(&data)?;
+
+        let signature: Signature = file.signature.parse()?;
+        let signer = signature.recover(manifest_message(&file.digests))?;
+        if signer != expected {
+            return Err(anyhow!("invalid manifest signature"));
+        }
+
+        file.digests
+            .into_iter()
+            .find(|e| e.prover == *prover && e.tag == tag)
+            .map(|e| e.digest)
+            .ok_or(anyhow!("no pinned digest of {:?} {}", prover, tag))
+    }
+
+    /// pin the install with the manifest. when the manifest is configured or the pin is
+    /// required (automatic install), refuse it without pinned digest, otherwise the digest
+    /// is pinned at first pull
+    pub async fn pin(&self, req: &mut PullRequest, required: bool) -> Result<()> {
+        if req.digest.is_some() || (self.source.is_none() && !required) {
+            return Ok(());
+        }
+
+        let digest = self
+            .digest(&req.prover, &req.tag)
+            .await
+            .map_err(|e| anyhow!("refuse to install without pinned digest: {}", e))?;
+        req.digest = Some(digest);
+        Ok(())
+    }
+}
+
+/// check the pulled image, pin it with the expected digest, or the repo digest at first
+/// manual pull.
+/// the mismatched image will be removed.
+pub async fn pin_image(
+    docker: &dyn ProverRuntime,
+    image: &str,
+    expected: Option<&str>,
+) -> Result<String> {
+    let digests = docker.digests(image).await?;
+
+    match expected {
+        Some(expected) => {
+            if match_digest(&digests, expected) {
+                Ok(expected.trim().trim_start_matches("sha256:").to_lowercase())
+            } else {
+                error!(
+                    "[Image] ALERT: pulled image {} mismatch digest {}, removed",
+                    image, expected
+                );
+                let _ = docker.remove(image).await;
+                Err(anyhow!("image digest mismatch"))
+            }
+        }
+        None => {
+            // prefer the manifest digest, it is same in all registries
+            let digest = digests.last().ok_or(anyhow!("Missing image digest"))?;
+            Ok(digest.clone())
+        }
+    }
+}
+
+/// pull the image of prover in the job, return the image and pinned digest.
+/// the pulled image is recorded, so gc only collects the images of provers
+async fn pull_image(
+    db: &ReDB,
+    docker: &dyn ProverRuntime,
+    jobs: &Jobs,
+    job: &str,
+    req: &PullRequest,
+) -> Result<(String, String)> {
+    let image = docker
+        .pull(
+            req.registry.as_deref(),
+            req.org.as_deref(),
+            &req.name,
+            &req.tag,
+            &|progress| jobs.progress(job, progress),
+        )
+        .await?;
+    let digest = pin_image(docker, &image, req.digest.as_deref()).await?;
+    db.add(&PulledImage {
+        id: image.clone(),
+        prover: req.prover,
+        created: Utc::now().timestamp(),
+    })?;
+    Ok((image, digest))
+}
+
+/// pull the image of prover in the job, pin the digest and save the prover,
+/// the local settings kept if the prover installed
+pub async fn install_prover(
+    db: &ReDB,
+    docker: &dyn ProverRuntime,
+    jobs: &Jobs,
+    job: &str,
+    req: PullRequest,
+) -> Result<()> {
+    let (image, digest) = pull_image(db, docker, jobs, job, &req).await?;
+
+    let now = Utc::now().timestamp();
+    let p = match db.get::<Prover>(Prover::to_key(&req.prover))? {
+        Some(mut p) => {
+            p.tag = req.tag;
+            p.image = image;
+            p.name = req.name;
+            p.overtime = req.overtime;
+            p.ptype = req.ptype;
+            p.types = req.types;
+            p.digest = Some(digest);
+            p.blocked = None;
+            p.registry = req.registry;
+            p.org = req.org;
+            p
+        }
+        None => Prover {
+            prover: req.prover,
+            tag: req.tag,
+            image,
+            name: req.name,
+            overtime: req.overtime,
+            ptype: req.ptype,
+            types: req.types,
+            created: now,
+            priority: 0,
+            parallel: None,
+            weight: None,
+            digest: Some(digest),
+            blocked: None,
+            registry: req.registry,
+            org: req.org,
+            previous: None,
+            rollback: None,
+        },
+    };
+    db.add(&p)
+}
+
+/// Transactional upgrade of installed prover: pull the new version, smoke test it
+/// with the sample inputs, then swap the prover. The previous image is kept for
+/// rollback in the grace period.
+pub struct Upgrade {
+    pub db: Arc<ReDB>,
+    pub files: TaskFiles,
+    pub docker: Arc<dyn ProverRuntime>,
+    pub jobs: Arc<Jobs>,
+    pub slots: Arc<Slots>,
+    pub zkvm: Option<String>,
+    /// seconds to keep the previous image
+    pub grace: i64,
+    /// pins the new version
+    pub manifest: Arc<DigestManifest>,
+}
+
+impl Upgrade {
+    pub async fn run(&self, job: &str, mut req: PullRequest) -> Result<()> {
+        self.manifest.pin(&mut req, false).await?;
+        let key = Prover::to_key(&req.prover);
+        let p = self.db.get::<Prover>(key)?.ok_or(anyhow!("No prover"))?;
+
+        // 1. pull new version, the current prover keeps running
+        let (image, digest) =
+            pull_image(&self.db, self.docker.as_ref(), &self.jobs, job, &req).await?;
+
+        // 2. smoke test, remove the new image when failure
+        if let Err(e) = self.smoke_test(&p, &image, req.overtime).await {
+            if image != p.image {
+                let _ = self.docker.remove(&image).await;
+            }
+            return Err(anyhow!("smoke test: {}", e));
+        }
+
+        // 3. swap, read again for the settings changed when upgrading
+        let now = Utc::now().timestamp();
+        let mut p = self.db.get::<Prover>(key)?.ok_or(anyhow!("No prover"))?;
+        let current = PreviousImage {
+            tag: p.tag.clone(),
+            image: p.image.clone(),
+            digest: p.digest.clone(),
+            until: now + self.grace,
+        };
+        if let Some(previous) = p.previous.replace(current) {
+            if previous.image != image && previous.image != p.image {
+                let _ = self.docker.remove(&previous.image).await;
+            }
+        }
+        p.tag = req.tag;
+        p.image = image;
+        p.overtime = req.overtime;
+        p.ptype = req.ptype;
+        p.types = req.types;
+        p.digest = Some(digest);
+        p.blocked = None;
+        p.rollback = None;
+        self.db.add(&p)
+    }
+
+    /// run the new image with the sample inputs, it must upload a proof before overtime.
+    /// skipped if no sample inputs.
+    async fn smoke_test(&self, p: &Prover, image: &str, overtime: u64) -> Result<()> {
+        let Ok(sample) = self
+            .files
+            .read_sample_input(&format!("{:?}", p.prover))
+            .await
+        else {
+            warn!(
+                "[Image] no sample inputs of {:?}, skip smoke test",
+                p.prover
+            );
+            return Ok(());
+        };
+        let (inputs, publics) = parse_task_input(sample).await?;
+
+        // waiting for free slots
+        let sid = format!("u-{:?}", p.prover);
+        let (weight, parallel) = self.slots.limits(p);
+        let deadline = Utc::now().timestamp() + overtime as i64;
+        while !self
+            .slots
+            .acquire(&sid, p.prover, weight, parallel, Utc::now().timestamp())
+        {
+            if Utc::now().timestamp() > deadline {
+                return Err(anyhow!("no free slots"));
+            }
+            sleep(Duration::from_secs(SMOKE_TEST_INTERVAL)).await;
+        }
+
+        let res = async {
+            self.files.write_task_input(&sid, inputs, publics).await?;
+            let zkvm = self.zkvm.as_deref().unwrap_or("");
+            self.docker
+                .run(image, &sid, zkvm, deadline, RunOption::default())
+                .await?;
+
+            // waiting the proof uploaded, or the container exited
+            let container = format!("{}-{}", image, sid);
+            loop {
+                sleep(Duration::from_secs(SMOKE_TEST_INTERVAL)).await;
+                if self.files.read_task_proof(&sid).await.is_ok() {
+                    return Ok(());
+                }
+                let running = matches!(
+                    self.docker.status(&container).await,
+                    Ok(Some(status)) if status.running
+                );
+                if !running {
+                    return self
+                        .files
+                        .read_task_proof(&sid)
+                        .await
+                        .map(|_| ())
+                        .map_err(|_| anyhow!("no proof"));
+                }
+                if Utc::now().timestamp() > deadline {
+                    let _ = self.docker.stop(&container).await;
+                    return Err(anyhow!("over time"));
+                }
+            }
+        }
+        .await;
+
+        self.slots.release(&sid);
+        let _ = self.files.remove_task_input(&sid).await;
+        res
+    }
+}
+
+/// swap back to the previous version of prover in grace period,
+/// the demoted version is kept as previous until the new grace ends
+pub fn rollback_prover(db: &ReDB, prover: &Address, until: i64) -> Result<Prover> {
+    let mut p = db
+        .get::<Prover>(Prover::to_key(prover))?
+        .ok_or(anyhow!("No prover"))?;
+    let previous = p.previous.take().ok_or(anyhow!("No previous version"))?;
+
+    warn!(
+        "[Image] rollback prover {:?}: {} => {}",
+        prover, p.tag, previous.tag
+    );
+    p.rollback = Some(p.tag.clone());
+    p.previous = Some(PreviousImage {
+        tag: std::mem::replace(&mut p.tag, previous.tag),
+        image: std::mem::replace(&mut p.image, previous.image),
+        digest: std::mem::replace(&mut p.digest, previous.digest),
+        until,
+    });
+    p.blocked = None;
+    db.add(&p)?;
+    Ok(p)
+}
+
+/// delete the previous images of provers after grace period
+pub async fn clean_previous(db: &ReDB, docker: &dyn ProverRuntime, now: i64) -> Result<()> {
+    let count = db.count::<Prover>()?;
+    let (provers, _) = db.list::<Prover>(0, count)?;
+    for mut p in provers {
+        let Some(previous) = &p.previous else {
+            continue;
+        };
+        if previous.until > now {
+            continue;
+        }
+
+        info!(
+            "[Image] delete previous image of prover {:?}: {}",
+            p.prover, previous.tag
+        );
+        if previous.image != p.image {
+            if let Err(e) = docker.remove(&previous.image).await {
+                warn!("[Image] delete image {}: {}", previous.image, e);
+            }
+        }
+        p.previous = None;
+        db.add(&p)?;
+    }
+    Ok(())
+}
+
+/// check the image of prover before run, block the prover when mismatched
+pub async fn verify_image(db: &ReDB, docker: &dyn ProverRuntime, p: &mut Prover) -> Result<()> {
+    if let Some(reason) = &p.blocked {
+        return Err(anyhow!("prover blocked: {}", reason));
+    }
+
+    let digests = docker.digests(&p.image).await?;
+    match &p.digest {
+        Some(digest) => {
+            if match_digest(&digests, digest) {
+                return Ok(());
+            }
+
+            error!(
+                "[Image] ALERT: image of prover {:?} mismatch digest {}, refuse to run",
+                p.prover, digest
+            );
+            p.blocked = Some("image digest mismatch".to_owned());
+            db.add(p)?;
+            Err(anyhow!("image digest mismatch"))
+        }
+        None => {
+            // installed before pinning, pin the local image
+            p.digest = digests.last().cloned();
+            db.add(p)?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestMiner;
+
+    #[test]
+    fn test_match_digest() {
+        let digests = vec!["aaa111".to_owned(), "bbb222".to_owned()];
+        assert!(match_digest(&digests, "aaa111"));
+        assert!(match_digest(&digests, "sha256:BBB222"));
+        assert!(!match_digest(&digests, "ccc333"));
+        assert!(!match_digest(&digests, ""));
+        assert!(!match_digest(&[], "aaa111"));
+    }
+
+    #[tokio::test]
+    async fn test_digest_manifest() {
+        use ethers::prelude::{LocalWallet, Signer};
+
+        let prover = Address::random();
+        let signer = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        let path = std::env::temp_dir().join(format!("pozk-manifest-{}", rand::random::<u64>()));
+        let write = |signature: Signature| {
+            let data = serde_json::json!({
+                "digests": [{ "prover": prover, "tag": "v2", "digest": "aaa111" }],
+                "signature": signature.to_string(),
+            });
+            std::fs::write(&path, data.to_string()).unwrap();
+        };
+        let message = format!("{:?}:v2:aaa111\n", prover);
+        write(signer.sign_message(&message).await.unwrap());
+
+        let cfg = ServiceConfig {
+            image_manifest: Some(path.to_string_lossy().into_owned()),
+            manifest_signer: Some(format!("{:?}", signer.address())),
+            ..Default::default()
+        };
+        let manifest = DigestManifest::new(&cfg);
+        assert_eq!(manifest.digest(&prover, "v2").await.unwrap(), "aaa111");
+        assert!(manifest.digest(&prover, "v3").await.is_err());
+        assert!(manifest.digest(&Address::random(), "v2").await.is_err());
+
+        // signed by others
+        let other = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        write(other.sign_message(&message).await.unwrap());
+        assert!(manifest.digest(&prover, "v2").await.is_err());
+
+        // the configured manifest must pin the install
+        let req = |tag: &str| PullRequest {
+            prover,
+            tag: tag.to_owned(),
+            name: "prover".to_owned(),
+            overtime: 60,
+            ptype: pozk_utils::ProverType::ZK,
+            types: String::new(),
+            digest: None,
+            registry: None,
+            org: None,
+        };
+        write(signer.sign_message(&message).await.unwrap());
+        let mut r = req("v2");
+        manifest.pin(&mut r, false).await.unwrap();
+        assert_eq!(r.digest.as_deref(), Some("aaa111"));
+        assert!(manifest.pin(&mut req("v3"), false).await.is_err());
+
+        // no manifest, pinned at first pull unless required
+        let manifest = DigestManifest::new(&ServiceConfig::default());
+        assert!(manifest.digest(&prover, "v2").await.is_err());
+        let mut r = req("v2");
+        manifest.pin(&mut r, false).await.unwrap();
+        assert!(r.digest.is_none());
+        assert!(manifest.pin(&mut req("v2"), true).await.is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upgrade() {
+        let cfg = ServiceConfig::default();
+        let miner = TestMiner::start(&cfg).await;
+        let ctx = &miner.ctx;
+        let prover = Address::random();
+        let image = miner.install(prover, "v1").await;
+        let sample = format!("{:?}", prover);
+        ctx.files
+            .write_sample_input(&sample, &[1, 2, 3], &[4, 5])
+            .await
+            .unwrap();
+
+        // without manifest, pinned at first pull, smoke test with the sample
+        let job = ctx.jobs.create("upgrade", prover, "prover:v2".to_owned());
+        let upgrade = Upgrade {
+            db: ctx.db.clone(),
+            files: ctx.files.clone(),
+            docker: ctx.docker.clone(),
+            jobs: ctx.jobs.clone(),
+            slots: ctx.slots.clone(),
+            zkvm: None,
+            grace: 60,
+            manifest: Arc::new(DigestManifest::new(&cfg)),
+        };
+        let req = PullRequest {
+            prover,
+            tag: "v2".to_owned(),
+            name: "prover".to_owned(),
+            overtime: 60,
+            ptype: pozk_utils::ProverType::ZK,
+            types: String::new(),
+            digest: None,
+            registry: None,
+            org: None,
+        };
+        upgrade.run(&job, req).await.unwrap();
+
+        let p = ctx
+            .db
+            .get::<Prover>(Prover::to_key(&prover))
+            .unwrap()
+            .unwrap();
+        assert_eq!(p.tag, "v2");
+        let digest = ctx.docker.digests(&p.image).await.unwrap().pop();
+        assert_eq!(p.digest, digest);
+        assert_eq!(p.previous.unwrap().image, image);
+        assert_eq!(miner.mock.containers().len(), 1);
+        assert!(ctx
+            .db
+            .contains::<PulledImage>(PulledImage::to_key(&p.image))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_rollback_prover() {
+        let path = std::env::temp_dir().join(format!("pozk-rollback-{}", rand::random::<u64>()));
+        let db = ReDB::new(&path, true).unwrap();
+        let prover = Address::random();
+        let p = Prover {
+            prover,
+            tag: "v2".to_owned(),
+            image: "prover:v2".to_owned(),
+            name: "prover".to_owned(),
+            overtime: 60,
+            ptype: pozk_utils::ProverType::from_byte(0),
+            types: String::new(),
+            created: 0,
+            priority: 0,
+            parallel: None,
+            weight: None,
+            digest: Some("bbb".to_owned()),
+            blocked: None,
+            registry: None,
+            org: None,
+            previous: Some(PreviousImage {
+                tag: "v1".to_owned(),
+                image: "prover:v1".to_owned(),
+                digest: Some("aaa".to_owned()),
+                until: 100,
+            }),
+            rollback: None,
+        };
+        db.add(&p).unwrap();
+
+        let p = rollback_prover(&db, &prover, 200).unwrap();
+        assert_eq!(p.tag, "v1");
+        assert_eq!(p.image, "prover:v1");
+        assert_eq!(p.digest.as_deref(), Some("aaa"));
+        assert_eq!(p.rollback.as_deref(), Some("v2"));
+        let previous = p.previous.unwrap();
+        assert_eq!(previous.tag, "v2");
+        assert_eq!(previous.until, 200);
+    }
+}
@@ -0,0 +1,199 @@
+use chrono::prelude::*;
+use ethers::prelude::Address;
+use pozk_docker::PullProgress;
+use rand::distributions::{Alphanumeric, DistString};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+/// keep the finished jobs for query
+const JOB_RETENTION: i64 = 86400; // 1 day
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Finished,
+    Failed,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct LayerProgress {
+    pub status: String,
+    pub current: Option<i64>,
+    pub total: Option<i64>,
+}
+
+/// The background job of prover image, e.g. pull
+#[derive(Serialize, Clone, Debug)]
+pub struct Job {
+    pub id: String,
+    pub kind: String,
+    pub prover: Address,
+    /// name:tag of image
+    pub image: String,
+    pub status: JobStatus,
+    /// progress of layers, layer id => progress
+    pub layers: BTreeMap<String, LayerProgress>,
+    /// the downloaded and total bytes of known layers
+    pub current: i64,
+    pub total: i64,
+    pub error: Option<String>,
+    pub created: i64,
+    pub updated: i64,
+}
+
+/// The background jobs in memory, and the updates to subscribers
+pub struct Jobs {
+    jobs: Mutex<HashMap<String, Job>>,
+    sender: broadcast::Sender<Job>,
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(128);
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+            sender,
+        }
+    }
+
+    /// create a running job, return the id
+    pub fn create(&self, kind: &str, prover: Address, image: String) -> String {
+        let now = Utc::now().timestamp();
+        let id = Alphanumeric.sample_string(&mut rand::thread_rng(), 12);
+        let job = Job {
+            id: id.clone(),
+            kind: kind.to_owned(),
+            prover,
+            image,
+            status: JobStatus::Running,
+            layers: BTreeMap::new(),
+            current: 0,
+            total: 0,
+            error: None,
+            created: now,
+            updated: now,
+        };
+
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|_, j| j.status == JobStatus::Running || j.updated + JOB_RETENTION > now);
+        jobs.insert(id.clone(), job);
+        id
+    }
+
+    /// update the layer progress of pulling
+    pub fn progress(&self, id: &str, p: PullProgress) {
+        if p.layer.is_empty() {
+            return;
+        }
+        self.update(id, |job| {
+            let layer = job.layers.entry(p.layer).or_default();
+            layer.status = p.status;
+            if p.total.is_some() {
+                layer.current = p.current;
+                layer.total = p.total;
+            }
+            job.current = job.layers.values().filter_map(|l| l.current).sum();
+            job.total = job.layers.values().filter_map(|l| l.total).sum();
+        });
+    }
+
+    /// the job finished, or failed with error
+    pub fn finish(&self, id: &str, res: &anyhow::Result<()>) {
+        self.update(id, |job| match res {
+            Ok(()) => job.status = JobStatus::Finished,
+            Err(e) => {
+                job.status = JobStatus::Failed;
+                job.error = Some(e.to_string());
+            }
+        });
+    }
+
+    /// the prover has a running job
+    pub fn running(&self, prover: &Address) -> bool {
+        self.jobs
+            .lock()
+            .unwrap()
+            .values()
+            .any(|j| j.prover == *prover && j.status == JobStatus::Running)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.lock().unwrap().get(id).cloned()
+    }
+
+    /// all jobs, newest first
+    pub fn list(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self.jobs.lock().unwrap().values().cloned().collect();
+        jobs.sort_by_key(|j| Reverse(j.created));
+        jobs
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Job> {
+        self.sender.subscribe()
+    }
+
+    fn update<F: FnOnce(&mut Job)>(&self, id: &str, f: F) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.get_mut(id) {
+            f(job);
+            job.updated = Utc::now().timestamp();
+            let _ = self.sender.send(job.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs() {
+        let jobs = Jobs::new();
+        let mut receiver = jobs.subscribe();
+        let id = jobs.create("pull", Address::zero(), "prover:v1".to_owned());
+
+        jobs.progress(
+            &id,
+            PullProgress {
+                layer: "a".to_owned(),
+                status: "Downloading".to_owned(),
+                current: Some(10),
+                total: Some(100),
+            },
+        );
+        jobs.progress(
+            &id,
+            PullProgress {
+                layer: "b".to_owned(),
+                status: "Downloading".to_owned(),
+                current: Some(50),
+                total: Some(50),
+            },
+        );
+        // status without bytes keeps the last progress
+        jobs.progress(
+            &id,
+            PullProgress {
+                layer: "b".to_owned(),
+                status: "Pull complete".to_owned(),
+                current: None,
+                total: None,
+            },
+        );
+        let job = jobs.get(&id).unwrap();
+        assert_eq!(job.current, 60);
+        assert_eq!(job.total, 150);
+        assert_eq!(job.layers["b"].status, "Pull complete");
+        assert_eq!(receiver.try_recv().unwrap().current, 10);
+
+        jobs.finish(&id, &Err(anyhow::anyhow!("denied")));
+        let job = jobs.get(&id).unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.error.as_deref(), Some("denied"));
+        assert_eq!(jobs.list().len(), 1);
+    }
+}
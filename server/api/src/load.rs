@@ -0,0 +1,200 @@
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+use std::time::{Duration, Instant};
+use sysinfo::{Disks, System};
+
+use crate::config::ServiceConfig;
+
+pub const MB: u64 = 1048576;
+
+/// admission reads the load again when older than this, cpu usage needs an interval
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// live load of the host, the inputs of admission
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LoadInfo {
+    /// global cpu usage (%)
+    pub cpu: f32,
+    /// available memory (MB)
+    pub free_memory: u64,
+    /// available disk space of the base path (MB)
+    pub free_disk: u64,
+    /// disk usage of prover images and base path (MB), updated by gc
+    pub disk_usage: u64,
+    /// why the host refuse new tasks, None if admitted
+    pub saturated: Option<String>,
+}
+
+/// the host name which runs the miner
+pub fn host_name() -> String {
+    System::host_name().unwrap_or_default()
+}
+
+/// Live host load shared by services, the host may run other workloads,
+/// so new tasks are only admitted when cpu, memory and disk have room.
+/// All limits are off (0) by default.
+pub struct HostLoad {
+    path: PathBuf,
+    /// max cpu usage (%), 0 means no limit
+    max_cpu: f32,
+    /// min available memory (MB), 0 means no limit
+    min_memory: u64,
+    /// min available disk space (MB), 0 means no limit
+    min_disk: u64,
+    /// disk quota (MB), 0 means no quota
+    quota: u64,
+    /// the last disk usage (MB)
+    usage: AtomicU64,
+    sys: Mutex<(System, Disks)>,
+    /// the last load and when it was read
+    last: Mutex<(LoadInfo, Instant)>,
+}
+
+impl HostLoad {
+    pub fn new(path: PathBuf, cfg: &ServiceConfig) -> Self {
+        let path = path.canonicalize().unwrap_or(path);
+        let mut sys = System::new();
+        sys.refresh_cpu_usage();
+        let disks = Disks::new_with_refreshed_list();
+
+        let load = Self {
+            path,
+            max_cpu: cfg.max_cpu_load,
+            min_memory: cfg.min_free_memory,
+            min_disk: cfg.min_free_disk,
+            quota: cfg.disk_quota,
+            usage: AtomicU64::new(0),
+            sys: Mutex::new((sys, disks)),
+            last: Mutex::new((LoadInfo::default(), Instant::now())),
+        };
+        load.refresh();
+        load
+    }
+
+    /// read the live load of host, cpu usage is the average since last refresh
+    pub fn refresh(&self) -> LoadInfo {
+        let mut info = {
+            let mut sys = self.sys.lock().unwrap();
+            let (sys, disks) = &mut *sys;
+            sys.refresh_cpu_usage();
+            sys.refresh_memory();
+            disks.refresh();
+
+            // the disk which mounted the base path
+            let free_disk = disks
+                .iter()
+                .filter(|d| self.path.starts_with(d.mount_point()))
+                .max_by_key(|d| d.mount_point().as_os_str().len())
+                .map(|d| d.available_space())
+                .unwrap_or(u64::MAX);
+
+            LoadInfo {
+                cpu: sys.global_cpu_usage(),
+                free_memory: sys.available_memory() / MB,
+                free_disk: free_disk / MB,
+                disk_usage: self.usage.load(Ordering::SeqCst),
+                saturated: None,
+            }
+        };
+        info.saturated = self.check(&info);
+
+        if let Some(reason) = &info.saturated {
+            warn!("[Load] host saturated: {}", reason);
+        }
+        *self.last.lock().unwrap() = (info.clone(), Instant::now());
+        info
+    }
+
+    /// the last load of host
+    pub fn info(&self) -> LoadInfo {
+        self.last.lock().unwrap().0.clone()
+    }
+
+    /// the disk usage (MB) collected by gc, checked with quota at next refresh
+    pub fn set_disk_usage(&self, usage: u64) {
+        self.usage.store(usage, Ordering::SeqCst);
+    }
+
+    /// check the host can take new tasks with the live load
+    pub fn admit(&self) -> bool {
+        let stale = self.last.lock().unwrap().1.elapsed() >= REFRESH_INTERVAL;
+        let info = if stale { self.refresh() } else { self.info() };
+        info.saturated.is_none()
+    }
+
+    fn check(&self, info: &LoadInfo) -> Option<String> {
+        if self.max_cpu > 0.0 && info.cpu > self.max_cpu {
+            return Some(format!("cpu {:.1}% > {:.1}%", info.cpu, self.max_cpu));
+        }
+        if info.free_memory < self.min_memory {
+            return Some(format!(
+                "memory {}MB < {}MB",
+                info.free_memory, self.min_memory
+            ));
+        }
+        if info.free_disk < self.min_disk {
+            return Some(format!("disk {}MB < {}MB", info.free_disk, self.min_disk));
+        }
+        if self.quota > 0 && info.disk_usage > self.quota {
+            return Some(format!(
+                "disk usage {}MB > quota {}MB",
+                info.disk_usage, self.quota
+            ));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(cpu: f32, free_memory: u64, free_disk: u64) -> LoadInfo {
+        LoadInfo {
+            cpu,
+            free_memory,
+            free_disk,
+            disk_usage: 0,
+            saturated: None,
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let cfg = ServiceConfig {
+            max_cpu_load: 90.0,
+            min_free_memory: 1024,
+            min_free_disk: 2048,
+            ..Default::default()
+        };
+        let load = HostLoad::new(PathBuf::from("."), &cfg);
+
+        assert!(load.check(&info(50.0, 4096, 4096)).is_none());
+        assert!(load.check(&info(95.0, 4096, 4096)).is_some());
+        assert!(load.check(&info(50.0, 512, 4096)).is_some());
+        assert!(load.check(&info(50.0, 4096, 1024)).is_some());
+
+        let cfg = ServiceConfig {
+            max_cpu_load: 0.0,
+            min_free_memory: 0,
+            min_free_disk: 0,
+            ..Default::default()
+        };
+        let load = HostLoad::new(PathBuf::from("."), &cfg);
+        assert!(load.check(&info(100.0, 0, 0)).is_none());
+
+        let cfg = ServiceConfig {
+            disk_quota: 1024,
+            ..cfg
+        };
+        let load = HostLoad::new(PathBuf::from("."), &cfg);
+        let mut over = info(50.0, 4096, 4096);
+        over.disk_usage = 2048;
+        assert!(load.check(&info(50.0, 4096, 4096)).is_none());
+        assert!(load.check(&over).is_some());
+    }
+}
@@ -3,25 +3,45 @@ extern crate tracing;
 
 mod app;
 mod config;
+mod context;
+mod gc;
+mod images;
+mod jobs;
+mod load;
 mod metrics;
 mod p2p;
+mod scheduler;
 mod service;
+mod slots;
+mod state;
+#[cfg(test)]
+mod testing;
 
 use app::App;
-use config::ApiConfig;
+use config::{ApiConfig, ServiceConfig};
+use context::SharedContext;
+use jobs::Jobs;
+use load::HostLoad;
 use metrics::{MetricsMessage, MetricsService};
 use p2p::{P2pMessage, P2pService};
 use service::MainService;
+use slots::Slots;
+use state::MinerState;
 
 use anyhow::Result;
 use clap::{Args, Parser};
 use ethers::prelude::*;
-use pozk_db::{Controller, DbConfig, MainController, ReDB};
-use pozk_docker::DockerManager;
+use pozk_db::{Controller, DbConfig, MainController, MinerStatus, Prover, ReDB};
+use pozk_docker::{DockerManager, ProverRuntime};
 use pozk_monitor::{MonitorConfig, Pool, Scan};
-use pozk_utils::{init_path_and_server, new_service_channel, pozk_rpc_url, pozk_zero_gas_url};
+use pozk_utils::{new_service_channel, pozk_rpc_url, pozk_zero_gas_url, ServiceMessage, TaskFiles};
 use serde::Deserialize;
 use std::{fs, path::PathBuf, sync::Arc, time::Duration};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::{oneshot, watch},
+    time::timeout,
+};
 
 // empty account: sk = 0, address = 0x7e5f4552091a69125d5dfcb7b8c2659029395bdf
 const DEFAULT_WALLET: &str = "0000000000000000000000000000000000000000000000000000000000000001";
@@ -61,7 +81,7 @@ struct Command {
     #[arg(short, long)]
     zero_gas: Option<String>,
 
-    /// Parallel tasks one time (Optional).
+    /// Parallel slots one time (Optional), a task takes the slots of its prover weight.
     #[arg(short, long)]
     parallel: Option<usize>,
 
@@ -72,6 +92,22 @@ struct Command {
     /// ZKVM proxy service urL, e.g. http://127.0.0.1:9099
     #[arg(short = 'k', long)]
     zkvm: Option<String>,
+
+    /// Benchmark provers after started (Optional), e.g. all | 0xabc..,0xdef..
+    #[arg(long)]
+    benchmark: Option<String>,
+
+    /// Fixture file of benchmark (Optional), json: {"inputs": "0x..", "publics": "0x.."},
+    /// default use the sample of past tasks
+    #[arg(long)]
+    benchmark_fixture: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Fixture {
+    inputs: String,
+    #[serde(default)]
+    publics: String,
 }
 
 #[derive(Args, Debug, Deserialize, Default)]
@@ -84,6 +120,10 @@ struct Config {
 
     #[clap(flatten)]
     monitor_config: MonitorConfig,
+
+    #[clap(flatten)]
+    #[serde(default)]
+    service_config: ServiceConfig,
 }
 
 #[tokio::main]
@@ -115,9 +155,10 @@ async fn setup() -> Result<()> {
     co.monitor_config.miner = args.miner.clone();
     co.monitor_config.zero_gas = zero_gas;
     co.api_config.miner = args.miner.clone();
+    co.monitor_config.sync_provers = co.service_config.auto_install()?.enabled();
 
     // setup base path
-    init_path_and_server(&args.base_path, &args.server);
+    let files = TaskFiles::new(&args.base_path);
     let base_path = PathBuf::from(&args.base_path);
 
     // setup database
@@ -127,17 +168,25 @@ async fn setup() -> Result<()> {
     };
 
     // setup docker
-    let docker = {
-        let dm = DockerManager::new(args.docker_proxy)?;
+    let docker: Arc<dyn ProverRuntime> = {
+        let auths = co.service_config.registry_auths()?;
+        let dm = DockerManager::new(args.docker_proxy, auths, &args.server)?;
         Arc::new(dm)
     };
 
+    // background jobs of prover images
+    let jobs = Arc::new(Jobs::new());
+
+    // live load of host
+    let load = Arc::new(HostLoad::new(base_path.clone(), &co.service_config));
+
     // start metrics service
     let (metrics_sender, cpu) = MetricsService::new(
         &args.network,
         args.miner.clone(),
         db.clone(),
         docker.clone(),
+        load.clone(),
         args.url.clone(),
     )?
     .run();
@@ -190,42 +239,111 @@ async fn setup() -> Result<()> {
     let (service_sender, service_receiver) = new_service_channel();
 
     // setup monitor
-    let pool_sender = Pool::new(&co.monitor_config, controller, ready)
-        .await?
-        .run();
-    Scan::new(co.monitor_config, service_sender.clone(), db.clone())
-        .await?
-        .run();
-
-    // setup api
-    App::new(
-        &co.api_config,
+    let pool_sender = Pool::new(
+        &co.monitor_config,
+        controller,
+        ready,
         db.clone(),
-        docker.clone(),
         service_sender.clone(),
-        p2p_sender.clone(),
-        &args.network,
-        endpoints,
-        args.url.clone(),
-        zkvm.clone(),
-    )?
+    )
+    .await?
     .run();
+    let (scan_stop, scan_stop_receiver) = watch::channel(false);
+    let scan = Scan::new(co.monitor_config, service_sender.clone(), db.clone())
+        .await?
+        .run(scan_stop_receiver);
+
+    // runtime state of miner, keep paused across restarts
+    let paused = db
+        .get::<MinerStatus>(MinerStatus::to_key())?
+        .map(|s| s.paused)
+        .unwrap_or(false);
+    if paused {
+        warn!("Miner is paused, not accepting new tasks");
+    }
+    let state = Arc::new(MinerState::new(paused));
+
+    // slots shared by all running tasks
+    let slots = Arc::new(Slots::new(parallel, &co.service_config));
+
+    let ctx = SharedContext {
+        db: db.clone(),
+        files,
+        docker,
+        jobs,
+        slots,
+        load,
+        state: state.clone(),
+        service_sender: service_sender.clone(),
+        p2p_sender,
+        url: args.url,
+        zkvm,
+        upgrade_grace: co.service_config.upgrade_grace,
+    };
+
+    // setup api
+    App::new(&co.api_config, &ctx, &args.network, endpoints)?.run();
 
     // setup main service
     MainService::new(
+        &ctx,
         pool_sender,
         metrics_sender,
-        p2p_sender,
         service_receiver,
-        db,
-        docker,
-        parallel,
-        args.url,
-        zkvm,
+        &co.service_config,
     )
-    .run(service_sender);
+    .run(service_sender.clone());
+
+    // self-benchmark of provers
+    if let Some(provers) = args.benchmark {
+        let fixture = if let Some(path) = args.benchmark_fixture {
+            let f: Fixture = serde_json::from_str(&fs::read_to_string(path)?)?;
+            let inputs = hex::decode(f.inputs.trim_start_matches("0x"))?;
+            let publics = hex::decode(f.publics.trim_start_matches("0x"))?;
+            Some((inputs, publics))
+        } else {
+            None
+        };
 
-    tokio::signal::ctrl_c().await?;
+        let provers = if provers == "all" {
+            let count = db.count::<Prover>()?;
+            let (data, _) = db.list::<Prover>(0, count)?;
+            data.iter().map(|p| p.prover).collect()
+        } else {
+            provers
+                .split(',')
+                .map(|p| p.trim().parse())
+                .collect::<Result<Vec<Address>, _>>()?
+        };
+        for prover in provers {
+            info!("Benchmark prover: {:?}", prover);
+            service_sender.send(ServiceMessage::Benchmark(prover, fixture.clone()))?;
+        }
+    }
+
+    // waiting for stop signal or drain request
+    let mut sigterm = signal(SignalKind::terminate())?;
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => info!("Received ctrl-c"),
+        _ = sigterm.recv() => info!("Received SIGTERM"),
+        _ = state.drain_requested() => info!("Received drain request"),
+    }
+
+    // drain: stop accepting, waiting for running tasks and pending txs
+    state.set_draining();
+    let drain_timeout = Duration::from_secs(co.service_config.drain_timeout);
+    let (done, drained) = oneshot::channel();
+    service_sender.send(ServiceMessage::Drain(done))?;
+    match timeout(drain_timeout, drained).await {
+        Ok(Ok(())) => info!("Drained all tasks"),
+        _ => warn!("Drain timeout, some tasks not finished"),
+    }
+
+    // stop scan after the cursor saved
+    let _ = scan_stop.send(true);
+    if timeout(Duration::from_secs(30), scan).await.is_err() {
+        warn!("Scan stop timeout");
+    }
 
     Ok(())
 }
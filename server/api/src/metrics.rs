@@ -1,8 +1,8 @@
 use anyhow::Result;
 use chrono::Utc;
 use ethers::prelude::*;
-use pozk_db::{Prover, ReDB};
-use pozk_docker::DockerManager;
+use pozk_db::{Prover, ReDB, ZeroGasWallet};
+use pozk_docker::ProverRuntime;
 use pozk_utils::pozk_metrics_url;
 use reqwest::Client;
 use serde_json::{json, Value};
@@ -14,6 +14,8 @@ use tokio::{
     time::interval,
 };
 
+use crate::load::HostLoad;
+
 pub const PROXY_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub enum MetricsMessage {
@@ -24,7 +26,8 @@ pub struct MetricsService {
     miner: String,
     wallet: Option<LocalWallet>,
     db: Arc<ReDB>,
-    docker: Arc<DockerManager>,
+    docker: Arc<dyn ProverRuntime>,
+    load: Arc<HostLoad>,
     os: String,
     gpu: String,
     cpu: u64,
@@ -45,7 +48,8 @@ impl MetricsService {
         network: &str,
         miner: String,
         db: Arc<ReDB>,
-        docker: Arc<DockerManager>,
+        docker: Arc<dyn ProverRuntime>,
+        load: Arc<HostLoad>,
         url: String,
     ) -> Result<Self> {
         let os = System::long_os_version().unwrap_or("Unknow".to_owned());
@@ -66,6 +70,7 @@ impl MetricsService {
             miner,
             db,
             docker,
+            load,
             os,
             gpu,
             cpu,
@@ -183,8 +188,11 @@ impl MetricsService {
             return Ok(());
         }
 
-        let signature = if let Some(wallet) = &self.wallet {
-            wallet.sign_message(message).await?.to_string()
+        let (signature, zero_gas) = if let Some(wallet) = &self.wallet {
+            let zero_gas = self
+                .db
+                .get::<ZeroGasWallet>(ZeroGasWallet::to_key(&wallet.address()))?;
+            (wallet.sign_message(message).await?.to_string(), zero_gas)
         } else {
             return Ok(());
         };
@@ -192,6 +200,8 @@ impl MetricsService {
         let data = json!({
             "miner": self.miner,
             "provers": provers,
+            "load": self.load.info(),
+            "zero_gas": zero_gas,
             "timestamp": Utc::now().timestamp(),
             "signature": signature,
         });
@@ -208,7 +218,7 @@ impl MetricsService {
 }
 
 pub async fn list_provers(
-    docker: &Arc<DockerManager>,
+    docker: &Arc<dyn ProverRuntime>,
     db: &Arc<ReDB>,
 ) -> Result<(Vec<Value>, String)> {
     let images = docker.list().await?;
@@ -228,8 +238,8 @@ pub async fn list_provers(
     let mut provers = vec![];
     let mut sign_provers = vec![];
     for p in data {
-        // check image
-        if images.contains_key(&p.image) {
+        // check image, the blocked prover cannot run
+        if images.contains_key(&p.image) && p.blocked.is_none() {
             let prover = format!("{:?}", p.prover);
             provers.push(json!({
                 "prover": prover,
@@ -0,0 +1,229 @@
+use ethers::prelude::{Address, U256};
+use pozk_utils::u256_to_f64;
+use std::collections::HashMap;
+
+use crate::config::ServiceConfig;
+
+/// weight of the newest sample in the proving time estimate
+const ESTIMATE_SMOOTHING: f64 = 0.3;
+
+/// task waiting for a free slot
+pub struct PendingTask {
+    pub tid: u64,
+    pub prover: Address,
+    pub fee: U256,
+    pub priority: i64,
+    /// slots cost of the task
+    pub weight: usize,
+    /// max parallel tasks of the prover
+    pub parallel: Option<usize>,
+    /// prover overtime (seconds), starts when the task accepted on chain
+    pub window: i64,
+    /// the time the task queued
+    pub queued: i64,
+}
+
+/// Order pending tasks by score, and drop the tasks which cannot be finished
+/// within the overtime, or waited longer than the stale window.
+pub struct Scheduler {
+    fee_weight: f64,
+    overtime_weight: f64,
+    priority_weight: f64,
+    duration_weight: f64,
+    /// seconds a task can wait in queue, 0 means no limit
+    stale: i64,
+    pending: HashMap<u64, PendingTask>,
+    /// estimated proving time of provers (seconds)
+    estimates: HashMap<Address, i64>,
+}
+
+impl Scheduler {
+    pub fn new(cfg: &ServiceConfig) -> Self {
+        Self {
+            fee_weight: cfg.fee_weight,
+            overtime_weight: cfg.overtime_weight,
+            priority_weight: cfg.priority_weight,
+            duration_weight: cfg.duration_weight,
+            stale: cfg.task_stale,
+            pending: HashMap::new(),
+            estimates: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, task: PendingTask) {
+        self.pending.insert(task.tid, task);
+    }
+
+    pub fn remove(&mut self, tid: u64) -> bool {
+        self.pending.remove(&tid).is_some()
+    }
+
+    /// drop all pending tasks, return the dropped tids
+    pub fn clear(&mut self) -> Vec<u64> {
+        self.pending.drain().map(|(tid, _)| tid).collect()
+    }
+
+    /// estimated proving time of the prover, 0 if never finished a task
+    pub fn estimate(&self, prover: &Address) -> i64 {
+        self.estimates.get(prover).copied().unwrap_or(0)
+    }
+
+    /// record a finished proving time of the prover
+    pub fn observe(&mut self, prover: Address, duration: i64) {
+        let duration = duration.max(0);
+        let next = match self.estimates.get(&prover) {
+            Some(old) => {
+                let v =
+                    *old as f64 * (1.0 - ESTIMATE_SMOOTHING) + duration as f64 * ESTIMATE_SMOOTHING;
+                v.round() as i64
+            }
+            None => duration,
+        };
+        self.estimates.insert(prover, next);
+    }
+
+    /// check the prover can finish a task within the overtime, which starts at accepting,
+    /// so the waiting time not counts
+    pub fn feasible(&self, prover: &Address, window: i64) -> bool {
+        self.estimate(prover) <= window
+    }
+
+    /// drop all tasks which cannot be finished or stale, return the dropped tids
+    pub fn prune(&mut self, now: i64) -> Vec<u64> {
+        let dropped: Vec<u64> = self
+            .pending
+            .values()
+            .filter(|t| {
+                !self.feasible(&t.prover, t.window)
+                    || (self.stale > 0 && now - t.queued > self.stale)
+            })
+            .map(|t| t.tid)
+            .collect();
+        for tid in dropped.iter() {
+            self.pending.remove(tid);
+        }
+        dropped
+    }
+
+    /// take the best task which fits the free slots, and the dropped tids
+    pub fn next(
+        &mut self,
+        now: i64,
+        fits: impl Fn(&PendingTask) -> bool,
+    ) -> (Option<PendingTask>, Vec<u64>) {
+        let dropped = self.prune(now);
+
+        let mut best: Option<(f64, u64)> = None;
+        for t in self.pending.values().filter(|t| fits(t)) {
+            let score = self.score(t);
+            let better = match best {
+                // same score, the older task first
+                Some((s, tid)) => score > s || (score == s && t.tid < tid),
+                None => true,
+            };
+            if better {
+                best = Some((score, t.tid));
+            }
+        }
+
+        let task = best.and_then(|(_, tid)| self.pending.remove(&tid));
+        (task, dropped)
+    }
+
+    fn score(&self, t: &PendingTask) -> f64 {
+        let window = t.window.max(1);
+        let fee = u256_to_f64(t.fee) / 1e18;
+        let estimate = self.estimate(&t.prover);
+        // the overtime remaining after proving, the more the safer
+        let remaining = (window - estimate).clamp(0, window) as f64 / window as f64;
+        let duration = estimate as f64 / window as f64;
+
+        self.fee_weight * fee
+            + self.priority_weight * t.priority as f64
+            + self.overtime_weight * remaining
+            - self.duration_weight * duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(tid: u64, prover: Address, fee: u64, priority: i64, window: i64) -> PendingTask {
+        PendingTask {
+            tid,
+            prover,
+            fee: U256::from(fee) * U256::exp10(18),
+            priority,
+            weight: 1,
+            parallel: None,
+            window,
+            queued: 0,
+        }
+    }
+
+    #[test]
+    fn test_order() {
+        let mut s = Scheduler::new(&ServiceConfig::default());
+        let p = Address::random();
+        s.push(task(1, p, 1, 0, 100));
+        s.push(task(2, p, 5, 0, 100));
+        s.push(task(3, p, 1, 10, 100));
+
+        assert_eq!(s.next(0, |_| true).0.map(|t| t.tid), Some(3));
+        assert_eq!(s.next(0, |_| true).0.map(|t| t.tid), Some(2));
+        assert_eq!(s.next(0, |_| true).0.map(|t| t.tid), Some(1));
+        assert!(s.next(0, |_| true).0.is_none());
+    }
+
+    #[test]
+    fn test_overtime_remaining() {
+        let mut s = Scheduler::new(&ServiceConfig::default());
+        let p = Address::random();
+        s.observe(p, 20);
+        s.push(task(1, p, 1, 0, 30));
+        s.push(task(2, p, 1, 0, 100));
+        s.push(task(3, p, 1, 0, 60));
+
+        // waiting not counts, the tasks are kept long after queued
+        assert_eq!(s.next(1000, |_| true).0.map(|t| t.tid), Some(2));
+        assert_eq!(s.next(1000, |_| true).0.map(|t| t.tid), Some(3));
+        assert_eq!(s.next(1000, |_| true).0.map(|t| t.tid), Some(1));
+    }
+
+    #[test]
+    fn test_drop_infeasible() {
+        let mut s = Scheduler::new(&ServiceConfig::default());
+        let slow = Address::random();
+        let fast = Address::random();
+        s.observe(slow, 80);
+        s.observe(fast, 10);
+        assert!(!s.feasible(&slow, 50));
+        s.push(task(1, slow, 10, 0, 50));
+        s.push(task(2, fast, 1, 0, 50));
+
+        let (next, dropped) = s.next(0, |_| true);
+        assert_eq!(next.map(|t| t.tid), Some(2));
+        assert_eq!(dropped, vec![1]);
+        assert!(s.next(0, |_| true).0.is_none());
+    }
+
+    #[test]
+    fn test_drop_stale() {
+        let cfg = ServiceConfig {
+            task_stale: 60,
+            ..Default::default()
+        };
+        let mut s = Scheduler::new(&cfg);
+        let p = Address::random();
+        s.push(task(1, p, 10, 0, 100));
+        s.push(PendingTask {
+            queued: 50,
+            ..task(2, p, 1, 0, 100)
+        });
+
+        let (next, dropped) = s.next(100, |_| true);
+        assert_eq!(next.map(|t| t.tid), Some(2));
+        assert_eq!(dropped, vec![1]);
+    }
+}
@@ -2,27 +2,54 @@ use anyhow::{anyhow, Result};
 use chrono::prelude::*;
 use ethers::prelude::{Address, Signer};
 use pozk_db::ReDB;
-use pozk_db::{MainController, Prover, Task};
-use pozk_docker::{DockerManager, RunOption};
+use pozk_db::{
+    Benchmark, BenchmarkStatus, ChainProver, MainController, MinerTest, MinerTestStatus, Prover,
+    Reward, Task, Unstaking,
+};
+use pozk_docker::{ProverRuntime, RunOption};
 use pozk_monitor::PoolMessage;
 use pozk_utils::{
-    is_valid_url, is_valid_zkvm, remove_task_input, write_task_input, write_task_proof,
-    ServiceMessage,
+    is_valid_url, is_valid_zkvm, parse_task_input, PullRequest, ServiceMessage, TaskFiles,
 };
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::{
-    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    sync::{
+        mpsc::{UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
     time::interval,
 };
 
+use crate::config::{AutoInstall, ServiceConfig};
+use crate::context::SharedContext;
+use crate::gc::DiskGc;
+use crate::images::{clean_previous, install_prover, verify_image, DigestManifest, Upgrade};
+use crate::jobs::Jobs;
+use crate::load::{host_name, HostLoad};
 use crate::metrics::MetricsMessage;
 use crate::p2p::P2pMessage;
+use crate::scheduler::{PendingTask, Scheduler};
+use crate::slots::Slots;
+use crate::state::{EpochInfo, MinerState};
+
+/// release the slots of accepting task if not started after this time
+const ACCEPT_TIMEOUT: i64 = 300; // 5min
+const TEST_SUBMIT_TIMEOUT: i64 = 600; // 10min, waiting the submit tx after deadline
 
 struct WaitingTask {
     image: String,
     prover: Address,
+    weight: usize,
+    inputs: Vec<u8>,
+    publics: Vec<u8>,
+}
+
+struct WaitingTest {
+    id: u64,
+    prover: Address,
+    overtime: i64,
     inputs: Vec<u8>,
     publics: Vec<u8>,
 }
@@ -33,53 +60,110 @@ pub struct MainService {
     p2p_sender: UnboundedSender<P2pMessage>,
     service_receiver: UnboundedReceiver<ServiceMessage>,
     db: Arc<ReDB>,
-    docker: Arc<DockerManager>,
+    /// the task files in base path
+    files: TaskFiles,
+    docker: Arc<dyn ProverRuntime>,
+    /// background jobs of prover images
+    jobs: Arc<Jobs>,
+    /// which approved provers installed automatically
+    auto_install: AutoInstall,
+    /// seconds to keep the previous image of upgraded prover
+    upgrade_grace: i64,
+    /// pins the images of automatic installs and upgrades
+    manifest: Arc<DigestManifest>,
+    /// garbage collection of images and task files
+    gc: Arc<DiskGc>,
+    /// seconds between garbage collections, 0 to disable
+    gc_interval: i64,
+    /// the last time of garbage collection
+    last_gc: i64,
     url: String,
     check_url: bool,
     zkvm: Option<String>,
-    task_parallel: usize,
-    /// task from API and not limit by parallel
+    /// host name, benchmarks are stored per host
+    host: String,
+    /// slots shared by on-chain tasks, API tasks and miner tests
+    slots: Arc<Slots>,
+    /// live load of host, refuse new tasks when saturated
+    load: Arc<HostLoad>,
+    /// runtime state of miner
+    state: Arc<MinerState>,
+    /// reply when drained
+    drain: Option<oneshot::Sender<()>>,
+    /// task from API, sid => overtime, slots acquired by API
     task_proxy: HashMap<String, i64>,
+    /// miner tests waiting for free slots
+    test_pending: VecDeque<WaitingTest>,
+    /// running miner tests, id => deadline
+    test_running: HashMap<u64, i64>,
     /// task send to this pool when already a task running
     task_onchain: BTreeMap<u64, WaitingTask>,
-    /// task need to accept if possible
-    task_pending: VecDeque<u64>,
-    /// running task with container, will check times, sid => (tid, created, overtime)
-    task_working: HashMap<String, (u64, i64, i64)>,
+    /// task need to accept if possible, ordered by score
+    task_pending: Scheduler,
+    /// running task with container, will check times, sid => (prover, created, overtime)
+    task_working: HashMap<String, (Address, i64, i64)>,
 }
 
 impl MainService {
     pub fn new(
+        ctx: &SharedContext,
         pool_sender: UnboundedSender<PoolMessage>,
         metrics_sender: UnboundedSender<MetricsMessage>,
-        p2p_sender: UnboundedSender<P2pMessage>,
         service_receiver: UnboundedReceiver<ServiceMessage>,
-        db: Arc<ReDB>,
-        docker: Arc<DockerManager>,
-        task_parallel: usize,
-        url: String,
-        zkvm: Option<String>,
+        cfg: &ServiceConfig,
     ) -> Self {
+        let SharedContext {
+            db,
+            files,
+            docker,
+            jobs,
+            slots,
+            load,
+            state,
+            p2p_sender,
+            url,
+            zkvm,
+            ..
+        } = ctx.clone();
         let check_url = is_valid_url(&url, true);
         if check_url {
             info!("[Service] checked url: {}", check_url);
         } else {
             warn!("[Service] checked url: {}", check_url);
         }
+        let gc = Arc::new(DiskGc::new(ctx, cfg));
+        let test_running = running_tests(&db).unwrap_or_else(|e| {
+            error!("[Service] load running miner tests: {}", e);
+            HashMap::new()
+        });
         Self {
             pool_sender,
             metrics_sender,
             p2p_sender,
             service_receiver,
             db,
+            files,
             docker,
+            jobs,
+            auto_install: cfg.auto_install().unwrap_or(AutoInstall::None),
+            upgrade_grace: cfg.upgrade_grace,
+            manifest: Arc::new(DigestManifest::new(cfg)),
+            gc,
+            gc_interval: cfg.gc_interval,
+            last_gc: 0,
             url,
             check_url,
             zkvm,
-            task_parallel,
+            host: host_name(),
+            slots,
+            load,
+            state,
+            drain: None,
             task_proxy: HashMap::new(),
+            test_pending: VecDeque::new(),
+            test_running,
             task_onchain: BTreeMap::new(),
-            task_pending: VecDeque::new(),
+            task_pending: Scheduler::new(cfg),
             task_working: HashMap::new(),
         }
     }
@@ -100,6 +184,7 @@ impl MainService {
                 if let Err(e) = handle(&mut self, msg).await {
                     error!("[Service] main error: {}", e);
                 }
+                check_drained(&mut self);
             }
         });
     }
@@ -107,10 +192,21 @@ impl MainService {
 
 async fn handle(app: &mut MainService, msg: ServiceMessage) -> Result<()> {
     match msg {
-        ServiceMessage::CreateTask(tid, prover, inputs, publics) => {
+        ServiceMessage::CreateTask(tid, prover, fee, inputs, publics) => {
+            if !app.state.accepting() {
+                debug!("[Service] not accepting, skip task: {}", tid);
+                return Ok(());
+            }
+
             // 1. check prover in local
             let key = Prover::to_key(&prover);
-            if let Some(p) = app.db.get::<Prover>(key)? {
+            if let Some(mut p) = app.db.get::<Prover>(key)? {
+                // the image mismatched, refuse to run before accepting on chain
+                if let Err(e) = verify_image(&app.db, app.docker.as_ref(), &mut p).await {
+                    warn!("[Service] skip task {}: {}", tid, e);
+                    return Ok(());
+                }
+
                 // check url status
                 if p.ptype.check_url() && !app.check_url {
                     return Ok(());
@@ -127,20 +223,40 @@ async fn handle(app: &mut MainService, msg: ServiceMessage) -> Result<()> {
                     }
                 }
 
+                // check the task can be finished in time, the overtime starts when accepted
+                let now = Utc::now().timestamp();
+                let window = p.overtime as i64;
+                if !app.task_pending.feasible(&prover, window) {
+                    warn!("[Service] task {} cannot be finished in time, skip", tid);
+                    return Ok(());
+                }
+
                 // 2. insert to waiting list
+                let (weight, parallel) = app.slots.limits(&p);
                 app.task_onchain.insert(
                     tid,
                     WaitingTask {
                         image: p.image,
                         prover,
+                        weight,
                         inputs,
                         publics,
                     },
                 );
 
-                // 3. parallel
-                if app.task_parallel == 0 {
-                    app.task_pending.push_back(tid);
+                // 3. parallel, defer when host saturated
+                let sid = tid.to_string();
+                if !app.load.admit() || !app.slots.acquire(&sid, prover, weight, parallel, now) {
+                    app.task_pending.push(PendingTask {
+                        tid,
+                        prover,
+                        fee,
+                        priority: p.priority,
+                        weight,
+                        parallel,
+                        window,
+                        queued: now,
+                    });
                     return Ok(());
                 }
 
@@ -152,8 +268,10 @@ async fn handle(app: &mut MainService, msg: ServiceMessage) -> Result<()> {
         }
         ServiceMessage::AcceptTask(tid, overtime, is_me) => {
             // 0. Cleanup waiting list
-            if let Some(pos) = app.task_pending.iter().position(|x| *x == tid) {
-                app.task_pending.remove(pos);
+            app.task_pending.remove(tid);
+            let sid = tid.to_string();
+            if !is_me && app.slots.release(&sid) {
+                accept_pending(app).await;
             }
             let task = app.task_onchain.remove(&tid).ok_or(anyhow!("No task"))?;
             if !is_me {
@@ -161,20 +279,34 @@ async fn handle(app: &mut MainService, msg: ServiceMessage) -> Result<()> {
             }
 
             // 1. save task to db
-            let sid = tid.to_string();
             let zkvm = app.zkvm.as_ref().map(|v| v.as_str()).unwrap_or("");
 
-            // 2. write data to file
-            write_task_input(&sid, task.inputs, task.publics).await?;
+            // 2. write data to file, and keep it as benchmark sample
+            let sample = format!("{:?}", task.prover);
+            let _ = app
+                .files
+                .write_sample_input(&sample, &task.inputs, &task.publics)
+                .await;
+            app.files
+                .write_task_input(&sid, task.inputs, task.publics)
+                .await?;
 
             // 3. start docker container to run, TODO we can do more about cpu & memory
-            let container = app
+            let created = Utc::now().timestamp();
+            app.slots.insert(&sid, task.prover, task.weight, created);
+            let container = match app
                 .docker
                 .run(&task.image, &sid, zkvm, overtime, RunOption::default())
-                .await?;
+                .await
+            {
+                Ok(container) => container,
+                Err(e) => {
+                    app.slots.release(&sid);
+                    return Err(e);
+                }
+            };
 
             // 4. save task to db
-            let created = Utc::now().timestamp();
             let t = Task {
                 tid,
                 prover: task.prover,
@@ -183,83 +315,162 @@ async fn handle(app: &mut MainService, msg: ServiceMessage) -> Result<()> {
                 container,
                 is_me: true,
                 over: false,
+                tx: None,
             };
             app.db.add(&t)?;
 
-            app.task_working.insert(sid, (tid, created, overtime));
-            if app.task_parallel > 0 {
-                app.task_parallel -= 1;
-            }
+            app.task_working
+                .insert(sid, (task.prover, created, overtime));
         }
         ServiceMessage::UploadProof(sid, proof) => {
+            // the smoke test of upgrade is waiting for the proof file
+            if sid.starts_with("u-") {
+                app.files.write_task_proof(&sid, proof).await?;
+                return Ok(());
+            }
+
             if let Some(over_at) = app.task_proxy.remove(&sid) {
                 let now = Utc::now().timestamp();
                 // check overtime, if over, just ignore it.
                 if now <= over_at {
-                    write_task_proof(&sid, proof).await?;
+                    app.files.write_task_proof(&sid, proof).await?;
                 }
                 // remove task input
-                let _ = remove_task_input(&sid).await;
+                let _ = app.files.remove_task_input(&sid).await;
+                if app.slots.release(&sid) {
+                    accept_pending(app).await;
+                }
                 return Ok(());
             }
 
-            // remove task/minertest
-            let _ = app.task_working.remove(&sid);
-            app.task_parallel += 1;
+            // remove task/minertest/benchmark, and learn the proving time
+            if let Some((prover, created, _)) = app.task_working.remove(&sid) {
+                let now = Utc::now().timestamp();
+                app.task_pending.observe(prover, now - created);
+            }
+
+            if sid.starts_with("b-") {
+                let _ = app.files.remove_task_input(&sid).await;
+                benchmark_finished(app, &sid, proof.len())?;
+                if app.slots.release(&sid) {
+                    accept_pending(app).await;
+                }
+                return Ok(());
+            }
 
             // check if has some task need accept
-            if let Some(tid) = app.task_pending.pop_front() {
-                app.pool_sender
-                    .send(PoolMessage::AcceptTask(tid, app.url.clone()))
-                    .expect("Missing pool");
+            if app.slots.release(&sid) {
+                accept_pending(app).await;
             }
 
-            tokio::spawn(upload_proof(
+            let uploading = upload_proof(
                 app.db.clone(),
+                app.files.clone(),
                 sid,
                 proof,
                 app.pool_sender.clone(),
-            ));
+            );
+            if app.state.is_draining() {
+                // make sure the submit is queued to pool before flushing
+                uploading.await;
+            } else {
+                tokio::spawn(uploading);
+            }
         }
-        ServiceMessage::ApproveProver(prover, version, overtime, ptype, types) => {
-            // 1. check prover in local
-            let key = Prover::to_key(&prover);
-            let new_tag = format!("v{}", version);
-
-            if let Some(mut p) = app.db.remove::<Prover>(key)? {
-                // 2. download new version
-                let image = app.docker.pull(&p.name, &new_tag).await?;
-                let old_image = p.image.clone();
+        ServiceMessage::ApproveProver(
+            prover,
+            version,
+            overtime,
+            ptype,
+            types,
+            minable,
+            approved,
+        ) => {
+            // 0. record the prover on chain
+            let now = Utc::now().timestamp();
+            let key = ChainProver::to_key(&prover);
+            let mut cp = app
+                .db
+                .get::<ChainProver>(key)?
+                .unwrap_or(ChainProver::new(prover, now));
+            cp.version = version;
+            cp.overtime = overtime;
+            cp.ptype = ptype;
+            cp.types = types.clone();
+            cp.minable = minable;
+            cp.approved = approved;
+            cp.updated = now;
+            app.db.add(&cp)?;
 
-                // 3. save new prover
-                p.image = image;
-                p.tag = new_tag;
-                p.overtime = overtime;
-                p.ptype = ptype;
-                p.types = types;
-                app.db.add(&p)?;
+            // 1. check prover in local, install it if allowed
+            let key = Prover::to_key(&prover);
+            let Some(p) = app.db.get::<Prover>(key)? else {
+                return auto_install(app, &cp);
+            };
 
-                // 4. delete old image
-                app.docker.remove(&old_image).await?;
+            // skip the old or same version, the version rolled back from, and the running jobs
+            let version_of = |tag: &str| tag.trim_start_matches('v').parse::<u64>().ok();
+            let local = version_of(&p.tag);
+            let rollback = p.rollback.as_deref().and_then(version_of);
+            if !approved
+                || local.map(|v| v >= version).unwrap_or(false)
+                || rollback.map(|v| v >= version).unwrap_or(false)
+                || app.jobs.running(&prover)
+            {
+                return Ok(());
             }
-        }
-        ServiceMessage::PullProver(prover, tag, name, overtime, ptype, types) => {
-            // 1. pull docker image
-            let image = app.docker.pull(&name, &tag).await?;
 
-            // 2. save to db
-            let created = Utc::now().timestamp();
-            let p = Prover {
+            // 2. upgrade in background, the previous version kept for rollback
+            let req = PullRequest {
                 prover,
-                tag,
-                image,
-                name,
+                tag: format!("v{}", version),
+                name: p.name,
                 overtime,
                 ptype,
                 types,
-                created,
+                digest: None,
+                registry: p.registry,
+                org: p.org,
+            };
+            let job = app
+                .jobs
+                .create("upgrade", prover, format!("{}:{}", req.name, req.tag));
+            let upgrade = Upgrade {
+                db: app.db.clone(),
+                files: app.files.clone(),
+                docker: app.docker.clone(),
+                jobs: app.jobs.clone(),
+                slots: app.slots.clone(),
+                zkvm: app.zkvm.clone(),
+                grace: app.upgrade_grace,
+                manifest: app.manifest.clone(),
             };
-            app.db.add(&p)?;
+            tokio::spawn(async move {
+                let res = upgrade.run(&job, req).await;
+                if let Err(e) = &res {
+                    error!("[Service] upgrade prover {:?}: {}", prover, e);
+                }
+                upgrade.jobs.finish(&job, &res);
+            });
+        }
+        ServiceMessage::RegisterProver(prover, name) => {
+            let now = Utc::now().timestamp();
+            let key = ChainProver::to_key(&prover);
+            let mut cp = app
+                .db
+                .get::<ChainProver>(key)?
+                .unwrap_or(ChainProver::new(prover, now));
+            cp.name = Some(name);
+            cp.updated = now;
+            app.db.add(&cp)?;
+
+            if app.db.get::<Prover>(Prover::to_key(&prover))?.is_none() {
+                auto_install(app, &cp)?;
+            }
+        }
+        ServiceMessage::PullProver(req, job) => {
+            // pull docker image in background, pin the digest and save to db
+            pull_prover(app, req, job, false);
         }
         ServiceMessage::RemoveProver(prover) => {
             let key = Prover::to_key(&prover);
@@ -291,28 +502,121 @@ async fn handle(app: &mut MainService, msg: ServiceMessage) -> Result<()> {
                 .expect("Missing p2p");
         }
         ServiceMessage::MinerTest(id, prover, overtime, inputs, publics) => {
-            // 1. check prover in local
-            let key = Prover::to_key(&prover);
-            if let Some(p) = app.db.get::<Prover>(key)? {
-                let sid = format!("m-{}-{}", id, overtime);
-                let zkvm = app.zkvm.as_ref().map(|v| v.as_str()).unwrap_or("");
-
-                // 2. write data to file
-                write_task_input(&sid, inputs, publics).await?;
-
-                // 3. start docker container to run, TODO we can do more about cpu & memory
-                let _container = app
-                    .docker
-                    .run(&p.image, &sid, zkvm, overtime, RunOption::default())
-                    .await?;
-
-                app.task_working
-                    .insert(sid, (0, Utc::now().timestamp(), overtime));
-                if app.task_parallel > 0 {
-                    app.task_parallel -= 1;
-                }
+            // miner tests still run when paused
+            if app.state.is_draining() {
+                debug!("[Service] draining, skip miner test: {}", id);
+                return Ok(());
+            }
+
+            let now = Utc::now().timestamp();
+            let key = MinerTest::to_key(id);
+            let mut t = app.db.get::<MinerTest>(&key)?.unwrap_or(MinerTest::new(
+                id,
+                prover,
+                MinerTestStatus::Running,
+                now,
+            ));
+            t.status = MinerTestStatus::Running;
+            t.deadline = overtime;
+            t.updated = now;
+            app.db.add(&t)?;
+            app.test_running.insert(id, overtime);
+
+            let sample = format!("{:?}", prover);
+            let _ = app
+                .files
+                .write_sample_input(&sample, &inputs, &publics)
+                .await;
+            let test = WaitingTest {
+                id,
+                prover,
+                overtime,
+                inputs,
+                publics,
+            };
+            if let Some(test) = start_miner_test(app, test).await? {
+                // waiting for free slots
+                app.test_pending.push_back(test);
             }
         }
+        ServiceMessage::MinerTestCancel(id) => {
+            let Some(mut t) = app.db.get::<MinerTest>(&MinerTest::to_key(id))? else {
+                return Ok(());
+            };
+            if t.status.is_final() {
+                return Ok(());
+            }
+            info!("[Service] miner test {} cancelled", id);
+            t.status = MinerTestStatus::Cancelled;
+            t.updated = Utc::now().timestamp();
+            app.db.add(&t)?;
+
+            // stop the running container and free the slots
+            app.test_running.remove(&id);
+            app.test_pending.retain(|w| w.id != id);
+            stop_working(app, &format!("m-{}", id)).await;
+            accept_pending(app).await;
+        }
+        ServiceMessage::MinerTestRequire(id, prover, amount) => {
+            let now = Utc::now().timestamp();
+            let key = MinerTest::to_key(id);
+            let mut t = app.db.get::<MinerTest>(&key)?.unwrap_or(MinerTest::new(
+                id,
+                prover,
+                MinerTestStatus::Required,
+                now,
+            ));
+            warn!("[Service] miner test {} required for prover {}", id, prover);
+            t.amount = Some(amount);
+            t.updated = now;
+            app.db.add(&t)?;
+        }
+        ServiceMessage::MinerTestSubmit(id) => {
+            app.test_running.remove(&id);
+            if let Some(mut t) = app.db.get::<MinerTest>(&MinerTest::to_key(id))? {
+                info!("[Service] miner test {} passed", id);
+                t.status = MinerTestStatus::Passed;
+                t.error = None;
+                t.updated = Utc::now().timestamp();
+                app.db.add(&t)?;
+            }
+        }
+        ServiceMessage::Benchmark(prover, fixture) => {
+            let now = Utc::now().timestamp();
+            let Some(mut p) = app.db.get::<Prover>(Prover::to_key(&prover))? else {
+                return Err(anyhow!("No prover: {:?}", prover));
+            };
+            if app.task_working.contains_key(&format!("b-{:?}", prover)) {
+                warn!("[Service] benchmark {:?} is running", prover);
+                return Ok(());
+            }
+            let mut b = Benchmark {
+                prover,
+                host: app.host.clone(),
+                image: p.image.clone(),
+                sample: if fixture.is_some() { "fixture" } else { "task" }.to_owned(),
+                status: BenchmarkStatus::Running,
+                started: now,
+                duration: None,
+                peak_memory: None,
+                proof_size: None,
+                overtime: p.overtime,
+                achievable: None,
+                error: None,
+                updated: now,
+            };
+
+            let started = match verify_image(&app.db, app.docker.as_ref(), &mut p).await {
+                Ok(()) => start_benchmark(app, &p, fixture, now).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = started {
+                error!("[Service] benchmark {:?} failed: {}", prover, e);
+                b.status = BenchmarkStatus::Failed;
+                b.error = Some(e.to_string());
+            }
+            app.db.add(&b)?;
+        }
         ServiceMessage::ApiTask(sid, over_at) => {
             app.task_proxy.insert(sid, over_at);
         }
@@ -328,37 +632,517 @@ async fn handle(app: &mut MainService, msg: ServiceMessage) -> Result<()> {
             }
             for i in clean {
                 app.task_working.remove(&i);
-                app.task_parallel += 1;
+                app.slots.release(&i);
+            }
+
+            // cleanup API tasks which over time
+            let mut clean = vec![];
+            for (sid, over_at) in app.task_proxy.iter() {
+                if now > *over_at {
+                    clean.push(sid.clone());
+                }
+            }
+            for i in clean {
+                app.task_proxy.remove(&i);
+                app.slots.release(&i);
+                let _ = app.files.remove_task_input(&i).await;
+                // the proof not fetched in time
+                let _ = app.files.remove_task_proof(&i).await;
+            }
+
+            // the previous images after upgrade grace
+            if let Err(e) = clean_previous(&app.db, app.docker.as_ref(), now).await {
+                error!("[Service] clean previous images: {}", e);
+            }
+
+            // collect the unused images and leftover task files in background
+            if app.gc_interval > 0 && now >= app.last_gc + app.gc_interval {
+                app.last_gc = now;
+                let mut working: HashSet<String> = app.task_working.keys().cloned().collect();
+                working.extend(app.task_proxy.keys().cloned());
+                working.extend(app.slots.holders_before(i64::MAX));
+                let gc = app.gc.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = gc.run(working).await {
+                        error!("[Service] garbage collection: {}", e);
+                    }
+                });
+            }
+
+            // read the live load of host before accepting more
+            app.load.refresh();
+
+            // release the accepting tasks which never started
+            for sid in app.slots.holders_before(now - ACCEPT_TIMEOUT) {
+                let upgrading = sid.starts_with("u-");
+                if !upgrading
+                    && !app.task_working.contains_key(&sid)
+                    && !app.task_proxy.contains_key(&sid)
+                {
+                    app.slots.release(&sid);
+                }
             }
+            accept_pending(app).await;
+
+            // drop the stale pending tasks
+            for tid in app.task_pending.prune(now) {
+                app.task_onchain.remove(&tid);
+            }
+
+            // the miner tests never submitted, the failed ones are checked in db
+            let over: Vec<u64> = app
+                .test_running
+                .iter()
+                .filter(|(_, d)| **d > 0 && **d + TEST_SUBMIT_TIMEOUT < now)
+                .map(|(id, _)| *id)
+                .collect();
+            for id in over {
+                app.test_running.remove(&id);
+                let running = app
+                    .db
+                    .get::<MinerTest>(&MinerTest::to_key(id))?
+                    .map(|t| t.status == MinerTestStatus::Running)
+                    .unwrap_or(false);
+                if running {
+                    error!("[Service] miner test {} over time", id);
+                    test_failed(&app.db, id, "over time".to_owned())?;
+                }
+            }
+        }
+        ServiceMessage::AcceptFailed(tid, reason) => {
+            warn!("[Service] accept task {} failed: {}", tid, reason);
+
+            // release the slots, if the task not started
+            let sid = tid.to_string();
+            app.task_onchain.remove(&tid);
+            if !app.task_working.contains_key(&sid) && app.slots.release(&sid) {
+                accept_pending(app).await;
+            }
+        }
+        ServiceMessage::SubmitFailed(id, is_test, reason) => {
+            if is_test {
+                error!("[Service] submit miner test {} failed: {}", id, reason);
+                test_failed(&app.db, id, reason)?;
+            } else {
+                error!("[Service] submit task {} failed: {}", id, reason);
+            }
+        }
+        ServiceMessage::Drain(done) => {
+            info!("[Service] draining");
+            app.state.set_draining();
+            app.drain = Some(done);
+
+            // drop the tasks not accepted yet
+            for tid in app.task_pending.clear() {
+                app.task_onchain.remove(&tid);
+            }
+            app.test_pending.clear();
+        }
+        ServiceMessage::MinerLabor(epoch, prover, work) => {
+            let key = Reward::to_key(epoch, &prover);
+            let mut r = app.db.get::<Reward>(&key)?.unwrap_or(Reward {
+                epoch,
+                prover,
+                ..Default::default()
+            });
+            r.work += work;
+            r.labors += 1;
+            r.updated = Utc::now().timestamp();
+            app.db.add(&r)?;
+        }
+        ServiceMessage::MinerCollect(epoch, prover, amount) => {
+            info!(
+                "[Service] collected reward of epoch {}: {} - {}",
+                epoch, prover, amount
+            );
+            let key = Reward::to_key(epoch, &prover);
+            let mut r = app.db.get::<Reward>(&key)?.unwrap_or(Reward {
+                epoch,
+                prover,
+                ..Default::default()
+            });
+            r.amount = Some(amount);
+            r.updated = Utc::now().timestamp();
+            app.db.add(&r)?;
+        }
+        ServiceMessage::AddUnstaking(epoch, amount) => {
+            let key = Unstaking::to_key(epoch);
+            let mut u = app.db.get::<Unstaking>(&key)?.unwrap_or(Unstaking {
+                epoch,
+                ..Default::default()
+            });
+            u.amount += amount;
+            u.updated = Utc::now().timestamp();
+            app.db.add(&u)?;
+        }
+        ServiceMessage::Epoch(height, start_time, period, maintenance) => {
+            let old = app.state.set_epoch(EpochInfo {
+                height,
+                start_time,
+                period,
+                maintenance,
+            });
+            if old.height != height {
+                info!("[Service] new epoch: {}", height);
+            }
+            if old.maintenance != maintenance {
+                if maintenance {
+                    warn!("[Service] network in maintenance, pause accepting tasks");
+                } else {
+                    info!("[Service] network maintenance over, accept tasks again");
+                    accept_pending(app).await;
+                }
+            }
+        }
+        ServiceMessage::Claim => {
+            app.pool_sender
+                .send(PoolMessage::Claim)
+                .expect("Missing pool");
+        }
+    }
+
+    Ok(())
+}
+
+/// when draining and all tasks done, flush the pool and reply
+fn check_drained(app: &mut MainService) {
+    if app.drain.is_none()
+        || app.slots.used() > 0
+        || !app.task_working.is_empty()
+        || !app.task_proxy.is_empty()
+    {
+        return;
+    }
+
+    info!("[Service] all tasks done, flushing pool");
+    if let Some(done) = app.drain.take() {
+        app.pool_sender
+            .send(PoolMessage::Flush(done))
+            .expect("Missing pool");
+    }
+}
+
+/// start a miner test if slots available, otherwise return it back
+async fn start_miner_test(app: &mut MainService, test: WaitingTest) -> Result<Option<WaitingTest>> {
+    // 1. check prover in local
+    let key = Prover::to_key(&test.prover);
+    let Some(mut p) = app.db.get::<Prover>(key)? else {
+        return Ok(None);
+    };
+    if let Err(e) = verify_image(&app.db, app.docker.as_ref(), &mut p).await {
+        test_failed(&app.db, test.id, e.to_string())?;
+        return Err(e);
+    }
+
+    let sid = format!("m-{}", test.id);
+    let (weight, parallel) = app.slots.limits(&p);
+    let now = Utc::now().timestamp();
+    if !app.load.admit() || !app.slots.acquire(&sid, test.prover, weight, parallel, now) {
+        return Ok(Some(test));
+    }
+
+    let zkvm = app.zkvm.as_ref().map(|v| v.as_str()).unwrap_or("");
+
+    // 2. write data to file
+    if let Err(e) = app
+        .files
+        .write_task_input(&sid, test.inputs, test.publics)
+        .await
+    {
+        app.slots.release(&sid);
+        return Err(e);
+    }
+
+    // 3. start docker container to run, TODO we can do more about cpu & memory
+    if let Err(e) = app
+        .docker
+        .run(&p.image, &sid, zkvm, test.overtime, RunOption::default())
+        .await
+    {
+        app.slots.release(&sid);
+        return Err(e);
+    }
+
+    app.task_working
+        .insert(sid, (test.prover, now, test.overtime));
+
+    if let Some(mut t) = app.db.get::<MinerTest>(&MinerTest::to_key(test.id))? {
+        t.started = now;
+        t.updated = now;
+        app.db.add(&t)?;
+    }
+    Ok(None)
+}
+
+/// run the prover with the sample inputs, the proof uploads to the same inner API of tasks
+async fn start_benchmark(
+    app: &mut MainService,
+    p: &Prover,
+    fixture: Option<(Vec<u8>, Vec<u8>)>,
+    now: i64,
+) -> Result<()> {
+    let sid = format!("b-{:?}", p.prover);
+    let (inputs, publics) = match fixture {
+        Some(fixture) => fixture,
+        None => {
+            let sample = app
+                .files
+                .read_sample_input(&format!("{:?}", p.prover))
+                .await
+                .map_err(|_| anyhow!("no sample inputs of past tasks"))?;
+            parse_task_input(sample).await?
         }
+    };
+
+    // benchmark takes the slots like a task
+    let (weight, parallel) = app.slots.limits(p);
+    if !app.slots.acquire(&sid, p.prover, weight, parallel, now) {
+        return Err(anyhow!("no free slots"));
     }
 
+    let zkvm = app.zkvm.as_deref().unwrap_or("");
+    let overtime = now + p.overtime as i64;
+    let started = async {
+        app.files.write_task_input(&sid, inputs, publics).await?;
+        app.docker
+            .run(&p.image, &sid, zkvm, overtime, RunOption::default())
+            .await
+    };
+    if let Err(e) = started.await {
+        app.slots.release(&sid);
+        return Err(e);
+    }
+    app.task_working
+        .insert(sid.clone(), (p.prover, now, overtime));
+
+    // watch the memory until the container exits
+    let db = app.db.clone();
+    let docker = app.docker.clone();
+    let container = format!("{}-{}", p.image, sid);
+    let key = Benchmark::to_key(&p.prover, &app.host);
+    tokio::spawn(async move {
+        match docker.peak_memory(&container).await {
+            Ok(peak) => {
+                if let Ok(Some(mut b)) = db.get::<Benchmark>(&key) {
+                    b.peak_memory = Some(peak);
+                    let _ = db.add(&b);
+                }
+            }
+            Err(e) => warn!("[Service] benchmark memory of {}: {}", container, e),
+        }
+    });
+
     Ok(())
 }
 
+/// record the proving time and proof size of the benchmark
+fn benchmark_finished(app: &MainService, sid: &str, proof_size: usize) -> Result<()> {
+    let prover: Address = sid.trim_start_matches("b-").parse()?;
+    let Some(mut b) = app
+        .db
+        .get::<Benchmark>(&Benchmark::to_key(&prover, &app.host))?
+    else {
+        return Ok(());
+    };
+
+    let now = Utc::now().timestamp();
+    let duration = now - b.started;
+    info!(
+        "[Service] benchmark {:?}: {}s, proof {} bytes",
+        prover, duration, proof_size
+    );
+    b.status = BenchmarkStatus::Finished;
+    b.duration = Some(duration);
+    b.proof_size = Some(proof_size);
+    b.achievable = Some(duration as u64 <= b.overtime);
+    b.updated = now;
+    app.db.add(&b)
+}
+
+/// pull and install the prover in background job, the automatic install must be pinned
+fn pull_prover(app: &MainService, mut req: PullRequest, job: String, automatic: bool) {
+    let (db, docker, jobs) = (app.db.clone(), app.docker.clone(), app.jobs.clone());
+    let manifest = app.manifest.clone();
+    tokio::spawn(async move {
+        let prover = req.prover;
+        let res = async {
+            if automatic {
+                manifest.pin(&mut req, true).await?;
+            } else if req.digest.is_none() {
+                // the manual pull is pinned by manifest if listed, otherwise at first pull
+                req.digest = manifest.digest(&req.prover, &req.tag).await.ok();
+            }
+            install_prover(&db, docker.as_ref(), &jobs, &job, req).await
+        }
+        .await;
+        if let Err(e) = &res {
+            error!("[Service] pull prover {:?}: {}", prover, e);
+        }
+        jobs.finish(&job, &res);
+    });
+}
+
+/// install the approved minable prover if allowed by the auto install policy
+fn auto_install(app: &MainService, cp: &ChainProver) -> Result<()> {
+    if !cp.approved || !cp.minable || !app.auto_install.allows(&cp.prover, &cp.ptype) {
+        return Ok(());
+    }
+    let Some(name) = &cp.name else {
+        return Ok(());
+    };
+    if app.jobs.running(&cp.prover) {
+        return Ok(());
+    }
+
+    info!(
+        "[Service] auto install prover {:?}: {} v{}",
+        cp.prover, name, cp.version
+    );
+    let req = PullRequest {
+        prover: cp.prover,
+        tag: format!("v{}", cp.version),
+        name: name.clone(),
+        overtime: cp.overtime,
+        ptype: cp.ptype,
+        types: cp.types.clone(),
+        digest: None,
+        registry: None,
+        org: None,
+    };
+    let job = app
+        .jobs
+        .create("install", cp.prover, format!("{}:{}", req.name, req.tag));
+    pull_prover(app, req, job, true);
+    Ok(())
+}
+
+/// stop the running container of task, and free its slots
+async fn stop_working(app: &mut MainService, sid: &str) {
+    let Some((prover, _, _)) = app.task_working.remove(sid) else {
+        return;
+    };
+    if let Ok(Some(p)) = app.db.get::<Prover>(Prover::to_key(&prover)) {
+        let container = format!("{}-{}", p.image, sid);
+        if let Err(e) = app.docker.stop(&container).await {
+            warn!("[Service] stop container {}: {}", container, e);
+        }
+    }
+    app.slots.release(sid);
+    let _ = app.files.remove_task_input(sid).await;
+}
+
+/// the miner test failed with reason
+/// the running miner tests in db when restart, id => deadline
+fn running_tests(db: &ReDB) -> Result<HashMap<u64, i64>> {
+    let count = db.count::<MinerTest>()?;
+    let (tests, _) = db.list::<MinerTest>(0, count)?;
+    Ok(tests
+        .into_iter()
+        .filter(|t| t.status == MinerTestStatus::Running)
+        .map(|t| (t.id, t.deadline))
+        .collect())
+}
+
+fn test_failed(db: &ReDB, id: u64, reason: String) -> Result<()> {
+    if let Some(mut t) = db.get::<MinerTest>(&MinerTest::to_key(id))? {
+        if !t.status.is_final() {
+            t.status = MinerTestStatus::Failed;
+            t.error = Some(reason);
+            t.updated = Utc::now().timestamp();
+            db.add(&t)?;
+        }
+    }
+    Ok(())
+}
+
+/// start the waiting miner tests first, then accept the best pending tasks until slots full
+/// or host saturated, drop the tasks which cannot be finished
+async fn accept_pending(app: &mut MainService) {
+    if app.state.is_draining() {
+        return;
+    }
+
+    let now = Utc::now().timestamp();
+    let mut tests = std::mem::take(&mut app.test_pending);
+    while let Some(test) = tests.pop_front() {
+        if test.overtime < now {
+            warn!("[Service] miner test {} over time, skip", test.id);
+            continue;
+        }
+        match start_miner_test(app, test).await {
+            Ok(Some(test)) => app.test_pending.push_back(test),
+            Ok(None) => {}
+            Err(e) => error!("[Service] miner test error: {}", e),
+        }
+    }
+
+    // paused, keep the pending tasks until stale
+    if !app.state.accepting() {
+        return;
+    }
+
+    loop {
+        let slots = &app.slots;
+        let admit = app.load.admit();
+        let (next, dropped) = app.task_pending.next(now, |t| {
+            admit && slots.available(&t.prover, t.weight, t.parallel)
+        });
+        for tid in dropped {
+            debug!("[Service] drop pending task: {}", tid);
+            app.task_onchain.remove(&tid);
+        }
+
+        let Some(task) = next else {
+            break;
+        };
+        let sid = task.tid.to_string();
+        app.slots
+            .acquire(&sid, task.prover, task.weight, task.parallel, now);
+        app.pool_sender
+            .send(PoolMessage::AcceptTask(task.tid, app.url.clone()))
+            .expect("Missing pool");
+    }
+}
+
 async fn upload_proof(
     db: Arc<ReDB>,
+    files: TaskFiles,
     sid: String,
     proof: Vec<u8>,
     pool_sender: UnboundedSender<PoolMessage>,
 ) {
     // 0. cleanup task input
-    let _ = remove_task_input(&sid).await;
+    let _ = files.remove_task_input(&sid).await;
 
     // 1. check task is miner test or task by tid
-    if sid.starts_with("m-") {
+    if let Some(id) = sid.strip_prefix("m-") {
         // Miner test
-        let s: Vec<&str> = sid.split('-').collect();
-        if s.len() != 3 {
+        let id: u64 = id.parse().unwrap_or(0);
+        let Ok(Some(mut t)) = db.get::<MinerTest>(&MinerTest::to_key(id)) else {
+            error!("[Service] unknown miner test: {}", sid);
+            return;
+        };
+        if t.status == MinerTestStatus::Cancelled {
+            info!("[Service] miner test {} cancelled, skip submit", id);
             return;
         }
-        let id: u64 = s[1].parse().unwrap_or(0);
-        let overtime: u64 = s[2].parse().unwrap_or(0);
-        let now = Utc::now().timestamp() as u64;
 
-        if overtime < now {
-            error!("CANNOT complete the miner tests");
-        } else {
+        let now = Utc::now().timestamp();
+        t.proof_size = Some(proof.len());
+        if t.started > 0 {
+            t.duration = Some(now - t.started);
+        }
+        t.updated = now;
+        let over = t.deadline < now;
+        if over {
+            error!("[Service] miner test {} over time", id);
+            t.status = MinerTestStatus::Failed;
+            t.error = Some("over time".to_owned());
+        }
+
+        // 2. update in db, before pool links the tx to it
+        let _ = db.add(&t);
+        if !over {
             pool_sender
                 .send(PoolMessage::SubmitMinerTest(id, proof))
                 .expect("Missing pool");
@@ -369,14 +1153,56 @@ async fn upload_proof(
 
     let tid: u64 = sid.parse().unwrap_or(0);
 
-    // 2. submit to chain
+    // 2. update in db, before pool links the tx to it
+    if let Ok(Some(mut t)) = db.get::<Task>(&Task::to_key(tid)) {
+        t.over = true;
+        let _ = db.add(&t);
+    }
+
+    // 3. submit to chain
     pool_sender
         .send(PoolMessage::SubmitTask(tid, proof))
         .expect("Missing pool");
+}
 
-    // 3. update in db
-    if let Ok(Some(mut t)) = db.get::<Task>(&Task::to_key(tid)) {
-        t.over = true;
-        let _ = db.add(&t);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TestMiner, WAITING};
+    use ethers::prelude::U256;
+    use tokio::time::timeout;
+
+    #[tokio::test]
+    async fn test_task_flow() {
+        let cfg = ServiceConfig::default();
+        let mut miner = TestMiner::start(&cfg).await;
+        let prover = Address::random();
+        miner.install(prover, "v1").await;
+        let sender = miner.ctx.service_sender.clone();
+
+        // 1. new task on chain, the miner accepts it
+        let task = ServiceMessage::CreateTask(1, prover, U256::one(), vec![1, 2, 3], vec![4, 5]);
+        sender.send(task).unwrap();
+        let msg = timeout(WAITING, miner.pool_receiver.recv()).await.unwrap();
+        assert!(matches!(msg, Some(PoolMessage::AcceptTask(1, _))));
+
+        // 2. accepted, the container proves and uploads by the inner API
+        let overtime = Utc::now().timestamp() + 60;
+        sender
+            .send(ServiceMessage::AcceptTask(1, overtime, true))
+            .unwrap();
+        let msg = timeout(WAITING, miner.pool_receiver.recv()).await.unwrap();
+        let Some(PoolMessage::SubmitTask(tid, proof)) = msg else {
+            panic!("proof not submitted");
+        };
+        assert_eq!(tid, 1);
+        // output length, publics as output, then inputs as proof
+        assert_eq!(proof, vec![0, 0, 0, 2, 4, 5, 1, 2, 3]);
+        let db = &miner.ctx.db;
+        assert!(db.get::<Task>(&Task::to_key(1)).unwrap().unwrap().over);
+
+        // the task input is kept as the sample of prover
+        let sample = format!("{:?}", prover);
+        assert!(miner.ctx.files.read_sample_input(&sample).await.is_ok());
     }
 }
@@ -0,0 +1,241 @@
+use ethers::prelude::Address;
+use pozk_db::Prover;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::config::{ProverLimit, ServiceConfig};
+
+struct Holder {
+    prover: Address,
+    weight: usize,
+    since: i64,
+}
+
+#[derive(Default)]
+struct Pool {
+    used: usize,
+    provers: HashMap<Address, usize>,
+    holders: HashMap<String, Holder>,
+}
+
+/// Weighted slots shared by all running tasks (on-chain tasks, API tasks and miner tests),
+/// works like a semaphore keyed by the task sid, with per-prover concurrency limits.
+///
+/// Invariants:
+/// - `used` is always the sum of the weights of all holders.
+/// - `acquire` never takes more than `capacity`, only `insert` (task already started) can.
+/// - a sid holds slots at most once, `release` gives back exactly what the sid holds.
+pub struct Slots {
+    capacity: usize,
+    /// slots cost of a ZK_VM task
+    zkvm_weight: usize,
+    /// limits of provers from config
+    limits: HashMap<Address, ProverLimit>,
+    pool: Mutex<Pool>,
+}
+
+impl Slots {
+    pub fn new(capacity: usize, cfg: &ServiceConfig) -> Self {
+        Self {
+            capacity,
+            zkvm_weight: cfg.zkvm_weight,
+            limits: cfg.provers(),
+            pool: Mutex::new(Pool::default()),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn used(&self) -> usize {
+        self.pool.lock().unwrap().used
+    }
+
+    /// slots cost and max parallel of the prover, local settings first
+    pub fn limits(&self, p: &Prover) -> (usize, Option<usize>) {
+        let limit = self.limits.get(&p.prover);
+        let weight = p
+            .weight
+            .or(limit.and_then(|l| l.weight))
+            .unwrap_or(if p.ptype.is_zkvm() {
+                self.zkvm_weight
+            } else {
+                1
+            });
+        let parallel = p.parallel.or(limit.and_then(|l| l.parallel));
+        (weight, parallel)
+    }
+
+    /// check a task of the prover can run now
+    pub fn available(&self, prover: &Address, weight: usize, parallel: Option<usize>) -> bool {
+        let pool = self.pool.lock().unwrap();
+        self.check(&pool, prover, weight, parallel)
+    }
+
+    /// take the slots for the task if available
+    pub fn acquire(
+        &self,
+        sid: &str,
+        prover: Address,
+        weight: usize,
+        parallel: Option<usize>,
+        now: i64,
+    ) -> bool {
+        let mut pool = self.pool.lock().unwrap();
+        if pool.holders.contains_key(sid) {
+            return true;
+        }
+        if !self.check(&pool, &prover, weight, parallel) {
+            return false;
+        }
+
+        self.hold(&mut pool, sid, prover, weight, now);
+        true
+    }
+
+    /// take the slots for the task even if not available (task already accepted on chain)
+    pub fn insert(&self, sid: &str, prover: Address, weight: usize, now: i64) {
+        let mut pool = self.pool.lock().unwrap();
+        if !pool.holders.contains_key(sid) {
+            self.hold(&mut pool, sid, prover, weight, now);
+        }
+    }
+
+    /// give back the slots of the task, return false if task not hold slots
+    pub fn release(&self, sid: &str) -> bool {
+        let mut pool = self.pool.lock().unwrap();
+        if let Some(h) = pool.holders.remove(sid) {
+            pool.used -= h.weight;
+            if let Some(n) = pool.provers.get_mut(&h.prover) {
+                *n -= 1;
+                if *n == 0 {
+                    pool.provers.remove(&h.prover);
+                }
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// the tasks which hold slots before the time
+    pub fn holders_before(&self, time: i64) -> Vec<String> {
+        let pool = self.pool.lock().unwrap();
+        pool.holders
+            .iter()
+            .filter(|(_, h)| h.since < time)
+            .map(|(sid, _)| sid.clone())
+            .collect()
+    }
+
+    fn check(&self, pool: &Pool, prover: &Address, weight: usize, parallel: Option<usize>) -> bool {
+        if let Some(max) = parallel {
+            if pool.provers.get(prover).copied().unwrap_or(0) >= max {
+                return false;
+            }
+        }
+
+        pool.used + self.clamp(weight) <= self.capacity
+    }
+
+    fn hold(&self, pool: &mut Pool, sid: &str, prover: Address, weight: usize, now: i64) {
+        let weight = self.clamp(weight);
+        pool.used += weight;
+        *pool.provers.entry(prover).or_insert(0) += 1;
+        pool.holders.insert(
+            sid.to_owned(),
+            Holder {
+                prover,
+                weight,
+                since: now,
+            },
+        );
+    }
+
+    /// a task heavier than all slots runs alone
+    fn clamp(&self, weight: usize) -> usize {
+        weight.clamp(1, self.capacity.max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slots(capacity: usize) -> Slots {
+        Slots::new(capacity, &ServiceConfig::default())
+    }
+
+    #[test]
+    fn test_acquire_capacity() {
+        let s = slots(4);
+        let p = Address::random();
+        assert!(s.acquire("1", p, 2, None, 0));
+        assert!(s.acquire("2", p, 2, None, 0));
+        assert!(!s.acquire("3", p, 1, None, 0));
+        assert_eq!(s.used(), 4);
+
+        // same sid never holds twice
+        assert!(s.acquire("1", p, 2, None, 0));
+        assert_eq!(s.used(), 4);
+    }
+
+    #[test]
+    fn test_release_exact() {
+        let s = slots(4);
+        let p = Address::random();
+        assert!(s.acquire("1", p, 3, None, 0));
+        assert!(s.release("1"));
+        assert!(!s.release("1"));
+        assert!(!s.release("unknown"));
+        assert_eq!(s.used(), 0);
+        assert!(s.acquire("2", p, 4, None, 0));
+    }
+
+    #[test]
+    fn test_prover_parallel() {
+        let s = slots(8);
+        let p1 = Address::random();
+        let p2 = Address::random();
+        assert!(s.acquire("1", p1, 1, Some(1), 0));
+        assert!(!s.acquire("2", p1, 1, Some(1), 0));
+        assert!(s.acquire("3", p2, 1, Some(1), 0));
+        assert!(s.release("1"));
+        assert!(s.acquire("2", p1, 1, Some(1), 0));
+    }
+
+    #[test]
+    fn test_insert_overcommit() {
+        let s = slots(2);
+        let p = Address::random();
+        assert!(s.acquire("1", p, 2, None, 0));
+        s.insert("2", p, 1, 0);
+        s.insert("2", p, 1, 0);
+        assert_eq!(s.used(), 3);
+        assert!(!s.available(&p, 1, None));
+
+        assert!(s.release("1"));
+        assert!(s.release("2"));
+        assert_eq!(s.used(), 0);
+    }
+
+    #[test]
+    fn test_heavy_task() {
+        let s = slots(2);
+        let p = Address::random();
+        assert!(s.acquire("1", p, 10, None, 0));
+        assert_eq!(s.used(), 2);
+        assert!(s.release("1"));
+        assert_eq!(s.used(), 0);
+    }
+
+    #[test]
+    fn test_holders_before() {
+        let s = slots(4);
+        let p = Address::random();
+        assert!(s.acquire("1", p, 1, None, 10));
+        assert!(s.acquire("2", p, 1, None, 20));
+        assert_eq!(s.holders_before(15), vec!["1".to_owned()]);
+    }
+}
@@ -0,0 +1,136 @@
+use serde::Serialize;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+use tokio::sync::Notify;
+
+/// The epoch of network, followed by scan
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct EpochInfo {
+    /// epoch height in contract
+    pub height: u64,
+    /// start time of the epoch (seconds)
+    pub start_time: u64,
+    /// epoch period (seconds)
+    pub period: u64,
+    /// network in maintenance, tasks will be reverted
+    pub maintenance: bool,
+}
+
+impl EpochInfo {
+    /// current epoch, the epoch contract updates height lazily
+    pub fn current(&self, now: u64) -> u64 {
+        if self.start_time + self.period < now {
+            self.height + 1
+        } else {
+            self.height
+        }
+    }
+
+    /// seconds to next epoch
+    pub fn next_in(&self, now: u64) -> u64 {
+        if self.period == 0 || now < self.start_time {
+            return 0;
+        }
+        let ends = self.start_time + self.period * ((now - self.start_time) / self.period + 1);
+        ends - now
+    }
+}
+
+/// Runtime state of the miner, shared by main service and API
+#[derive(Default)]
+pub struct MinerState {
+    /// stop accepting new tasks, and exit when current work done
+    draining: AtomicBool,
+    /// stop accepting new tasks by operator, persisted in db
+    paused: AtomicBool,
+    /// drain requested from API
+    drain: Notify,
+    /// the latest epoch from chain
+    epoch: Mutex<EpochInfo>,
+}
+
+impl MinerState {
+    pub fn new(paused: bool) -> Self {
+        Self {
+            paused: AtomicBool::new(paused),
+            ..Default::default()
+        }
+    }
+
+    /// check the miner can take new tasks
+    pub fn accepting(&self) -> bool {
+        !self.is_draining() && !self.is_paused() && !self.in_maintenance()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn set_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    /// network in maintenance, tasks are paused automatically
+    pub fn in_maintenance(&self) -> bool {
+        self.epoch.lock().unwrap().maintenance
+    }
+
+    pub fn epoch(&self) -> EpochInfo {
+        *self.epoch.lock().unwrap()
+    }
+
+    /// update the epoch, return the old one
+    pub fn set_epoch(&self, epoch: EpochInfo) -> EpochInfo {
+        std::mem::replace(&mut *self.epoch.lock().unwrap(), epoch)
+    }
+
+    /// ask the main process to drain and exit
+    pub fn request_drain(&self) {
+        self.drain.notify_one();
+    }
+
+    /// waiting for drain request
+    pub async fn drain_requested(&self) {
+        self.drain.notified().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epoch() {
+        let epoch = EpochInfo {
+            height: 10,
+            start_time: 1000,
+            period: 100,
+            maintenance: false,
+        };
+        assert_eq!(epoch.current(1050), 10);
+        assert_eq!(epoch.next_in(1050), 50);
+        assert_eq!(epoch.current(1100), 10);
+        assert_eq!(epoch.current(1101), 11);
+        assert_eq!(epoch.next_in(1120), 80);
+        assert_eq!(EpochInfo::default().next_in(1000), 0);
+
+        let state = MinerState::new(false);
+        assert!(state.accepting());
+        state.set_epoch(EpochInfo {
+            maintenance: true,
+            ..epoch
+        });
+        assert!(!state.accepting());
+        assert!(!state.is_paused());
+    }
+}
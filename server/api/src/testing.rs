@@ -0,0 +1,119 @@
+use ethers::prelude::Address;
+use pozk_db::{Prover, ReDB};
+use pozk_docker::{MockRuntime, ProverRuntime};
+use pozk_monitor::PoolMessage;
+use pozk_utils::{new_service_channel, ProverType, TaskFiles};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use crate::app::App;
+use crate::config::{ApiConfig, ServiceConfig};
+use crate::context::SharedContext;
+use crate::jobs::Jobs;
+use crate::load::HostLoad;
+use crate::metrics::MetricsMessage;
+use crate::p2p::P2pMessage;
+use crate::service::MainService;
+use crate::slots::Slots;
+use crate::state::MinerState;
+
+/// max waiting time of the messages from service
+pub const WAITING: Duration = Duration::from_secs(10);
+
+/// A miner in process with the mock docker runtime, the API and main service,
+/// in its own base path and inner API server
+pub struct TestMiner {
+    pub ctx: SharedContext,
+    pub mock: MockRuntime,
+    pub path: PathBuf,
+    pub pool_receiver: UnboundedReceiver<PoolMessage>,
+    _metrics_receiver: UnboundedReceiver<MetricsMessage>,
+    _p2p_receiver: UnboundedReceiver<P2pMessage>,
+}
+
+impl TestMiner {
+    pub async fn start(cfg: &ServiceConfig) -> Self {
+        // the inner API for containers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = format!("http://{}", listener.local_addr().unwrap());
+        let path = std::env::temp_dir().join(format!("pozk-test-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&path).unwrap();
+
+        let mock = MockRuntime::new(&server);
+        let (pool_sender, pool_receiver) = unbounded_channel();
+        let (metrics_sender, metrics_receiver) = unbounded_channel();
+        let (p2p_sender, p2p_receiver) = unbounded_channel();
+        let (sender, receiver) = new_service_channel();
+        let ctx = SharedContext {
+            db: Arc::new(ReDB::new(&path.join("db"), true).unwrap()),
+            files: TaskFiles::new(&path),
+            docker: Arc::new(mock.clone()),
+            jobs: Arc::new(Jobs::new()),
+            slots: Arc::new(Slots::new(4, cfg)),
+            load: Arc::new(HostLoad::new(path.clone(), cfg)),
+            state: Arc::new(MinerState::new(false)),
+            service_sender: sender.clone(),
+            p2p_sender,
+            url: String::new(),
+            zkvm: None,
+            upgrade_grace: cfg.upgrade_grace,
+        };
+
+        let api = ApiConfig {
+            miner: format!("{:?}", Address::random()),
+            ..Default::default()
+        };
+        let app = App::new(&api, &ctx, "localhost", "http://127.0.0.1:8545".to_owned()).unwrap();
+        tokio::spawn(async move { axum::serve(listener, app.router()).await });
+        MainService::new(&ctx, pool_sender, metrics_sender, receiver, cfg).run(sender);
+
+        Self {
+            ctx,
+            mock,
+            path,
+            pool_receiver,
+            _metrics_receiver: metrics_receiver,
+            _p2p_receiver: p2p_receiver,
+        }
+    }
+
+    /// pull the image of tag and install the prover, return the image
+    pub async fn install(&self, prover: Address, tag: &str) -> String {
+        let image = self
+            .mock
+            .pull(None, None, "prover", tag, &|_| {})
+            .await
+            .unwrap();
+        self.ctx
+            .db
+            .add(&Prover {
+                prover,
+                tag: tag.to_owned(),
+                image: image.clone(),
+                name: "prover".to_owned(),
+                overtime: 60,
+                ptype: ProverType::ZK,
+                types: String::new(),
+                created: 0,
+                priority: 0,
+                parallel: None,
+                weight: None,
+                digest: None,
+                blocked: None,
+                registry: None,
+                org: None,
+                previous: None,
+                rollback: None,
+            })
+            .unwrap();
+        image
+    }
+}
+
+impl Drop for TestMiner {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,66 @@
+use ethers::types::Address;
+use redb::TableDefinition;
+use serde::{Deserialize, Serialize};
+
+use crate::redb::{BaseTableDefinition, KvTable};
+
+const BENCHMARKS: BaseTableDefinition = TableDefinition::new("benchmarks");
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BenchmarkStatus {
+    Running,
+    Finished,
+    Failed,
+}
+
+/// The latest self-benchmark of a prover on a host
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Benchmark {
+    pub prover: Address,
+    pub host: String,
+    /// the image id of prover when benchmark
+    pub image: String,
+    /// the sample inputs: "task" from past tasks, or "fixture"
+    pub sample: String,
+    pub status: BenchmarkStatus,
+    pub started: i64,
+    /// seconds of proving
+    pub duration: Option<i64>,
+    /// peak memory of the container (bytes)
+    pub peak_memory: Option<u64>,
+    /// bytes of the proof
+    pub proof_size: Option<usize>,
+    /// the overtime of prover (seconds)
+    pub overtime: u64,
+    /// the proving finished before the overtime
+    pub achievable: Option<bool>,
+    pub error: Option<String>,
+    pub updated: i64,
+}
+
+impl Benchmark {
+    pub fn to_key(prover: &Address, host: &str) -> Vec<u8> {
+        let mut key = prover.as_bytes().to_vec();
+        key.extend(host.as_bytes());
+        key
+    }
+}
+
+impl KvTable for Benchmark {
+    fn table<'a>() -> BaseTableDefinition<'a> {
+        BENCHMARKS
+    }
+
+    fn key(&self) -> Vec<u8> {
+        Self::to_key(&self.prover, &self.host)
+    }
+
+    fn to_value(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or(vec![])
+    }
+
+    fn from_value(_key: &[u8], value: &[u8]) -> Option<Self> {
+        serde_json::from_slice(value).ok()
+    }
+}
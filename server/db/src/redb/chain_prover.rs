@@ -0,0 +1,61 @@
+use ethers::types::Address;
+use pozk_utils::ProverType;
+use redb::TableDefinition;
+use serde::{Deserialize, Serialize};
+
+use crate::redb::{BaseTableDefinition, KvTable};
+
+const CHAIN_PROVERS: BaseTableDefinition = TableDefinition::new("chain_provers");
+
+/// The prover registered on chain, the source of auto install
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChainProver {
+    pub prover: Address,
+    /// the image name, from register or upgrade
+    pub name: Option<String>,
+    pub version: u64,
+    pub overtime: u64,
+    pub ptype: ProverType,
+    pub types: String,
+    pub minable: bool,
+    pub approved: bool,
+    pub updated: i64,
+}
+
+impl ChainProver {
+    pub fn new(prover: Address, now: i64) -> Self {
+        Self {
+            prover,
+            name: None,
+            version: 0,
+            overtime: 0,
+            ptype: ProverType::ZK,
+            types: String::new(),
+            minable: false,
+            approved: false,
+            updated: now,
+        }
+    }
+
+    pub fn to_key(prover: &Address) -> &[u8] {
+        prover.as_bytes()
+    }
+}
+
+impl KvTable for ChainProver {
+    fn table<'a>() -> BaseTableDefinition<'a> {
+        CHAIN_PROVERS
+    }
+
+    fn key(&self) -> Vec<u8> {
+        Self::to_key(&self.prover).to_vec()
+    }
+
+    fn to_value(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or(vec![])
+    }
+
+    fn from_value(_key: &[u8], value: &[u8]) -> Option<Self> {
+        serde_json::from_slice(value).ok()
+    }
+}
@@ -0,0 +1,40 @@
+use redb::TableDefinition;
+use serde::{Deserialize, Serialize};
+
+use crate::redb::{BaseTableDefinition, KvTable};
+
+const MINER_STATUS: BaseTableDefinition = TableDefinition::new("miner_status");
+const MINER_STATUS_KEY: &str = "pozk_miner_status";
+
+/// Runtime status of miner which keep across restarts
+#[derive(Default, Serialize, Deserialize)]
+pub struct MinerStatus {
+    /// stop accepting new tasks by operator
+    pub paused: bool,
+    /// the time of last pause/resume
+    pub updated: i64,
+}
+
+impl MinerStatus {
+    pub fn to_key<'a>() -> &'a [u8] {
+        MINER_STATUS_KEY.as_bytes()
+    }
+}
+
+impl KvTable for MinerStatus {
+    fn table<'a>() -> BaseTableDefinition<'a> {
+        MINER_STATUS
+    }
+
+    fn key(&self) -> Vec<u8> {
+        Self::to_key().to_vec()
+    }
+
+    fn to_value(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or(vec![])
+    }
+
+    fn from_value(_key: &[u8], value: &[u8]) -> Option<Self> {
+        serde_json::from_slice(value).ok()
+    }
+}
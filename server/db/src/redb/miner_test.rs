@@ -0,0 +1,100 @@
+use ethers::types::{Address, U256};
+use redb::TableDefinition;
+use serde::{Deserialize, Serialize};
+
+use crate::redb::{BaseTableDefinition, KvTable};
+
+const MINER_TESTS: BaseTableDefinition = TableDefinition::new("miner_tests");
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MinerTestStatus {
+    /// required by stake, waiting to be created
+    Required,
+    /// created, proving in local
+    Running,
+    /// proof submitted and accepted by chain
+    Passed,
+    /// proof failed, rejected or over time
+    Failed,
+    /// cancelled on chain
+    Cancelled,
+}
+
+impl MinerTestStatus {
+    /// the test will never change again
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            MinerTestStatus::Passed | MinerTestStatus::Failed | MinerTestStatus::Cancelled
+        )
+    }
+}
+
+/// The miner test (qualification) of a prover, and its outcome
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MinerTest {
+    pub id: u64,
+    pub prover: Address,
+    /// the staking amount which requires the test
+    pub amount: Option<U256>,
+    /// the proof must be submitted before it, 0 if not created
+    pub deadline: i64,
+    /// the time started proving, 0 if not started
+    #[serde(default)]
+    pub started: i64,
+    /// seconds of proving
+    #[serde(default)]
+    pub duration: Option<i64>,
+    /// bytes of the proof
+    #[serde(default)]
+    pub proof_size: Option<usize>,
+    /// the submit tx in pool
+    #[serde(default)]
+    pub tx: Option<u64>,
+    pub status: MinerTestStatus,
+    pub error: Option<String>,
+    pub created: i64,
+    pub updated: i64,
+}
+
+impl MinerTest {
+    pub fn new(id: u64, prover: Address, status: MinerTestStatus, now: i64) -> Self {
+        Self {
+            id,
+            prover,
+            amount: None,
+            deadline: 0,
+            started: 0,
+            duration: None,
+            proof_size: None,
+            tx: None,
+            status,
+            error: None,
+            created: now,
+            updated: now,
+        }
+    }
+
+    pub fn to_key(id: u64) -> [u8; 8] {
+        id.to_be_bytes()
+    }
+}
+
+impl KvTable for MinerTest {
+    fn table<'a>() -> BaseTableDefinition<'a> {
+        MINER_TESTS
+    }
+
+    fn key(&self) -> Vec<u8> {
+        Self::to_key(self.id).to_vec()
+    }
+
+    fn to_value(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or(vec![])
+    }
+
+    fn from_value(_key: &[u8], value: &[u8]) -> Option<Self> {
+        serde_json::from_slice(value).ok()
+    }
+}
@@ -1,11 +1,29 @@
+mod benchmark;
+mod chain_prover;
 mod controller;
+mod miner;
+mod miner_test;
 mod prover;
+mod pulled_image;
+mod reward;
 mod scan;
 mod task;
+mod tx;
+mod unstaking;
+mod zero_gas;
+pub use benchmark::{Benchmark, BenchmarkStatus};
+pub use chain_prover::ChainProver;
 pub use controller::{Controller, MainController};
-pub use prover::Prover;
+pub use miner::MinerStatus;
+pub use miner_test::{MinerTest, MinerTestStatus};
+pub use prover::{PreviousImage, Prover};
+pub use pulled_image::PulledImage;
+pub use reward::Reward;
 pub use scan::ScanBlock;
 pub use task::Task;
+pub use tx::{Tx, TxKind, TxStatus};
+pub use unstaking::Unstaking;
+pub use zero_gas::ZeroGasWallet;
 
 use anyhow::{anyhow, Result};
 use redb::{Database, ReadableTable, ReadableTableMetadata, TableDefinition};
@@ -44,10 +62,19 @@ impl ReDB {
         // init all tables
         let txn = db.begin_write()?;
         {
+            let _ = txn.open_table(Benchmark::table());
+            let _ = txn.open_table(ChainProver::table());
             let _ = txn.open_table(Controller::table());
             let _ = txn.open_table(MainController::table());
+            let _ = txn.open_table(MinerStatus::table());
+            let _ = txn.open_table(MinerTest::table());
             let _ = txn.open_table(Prover::table());
+            let _ = txn.open_table(PulledImage::table());
+            let _ = txn.open_table(Reward::table());
             let _ = txn.open_table(Task::table());
+            let _ = txn.open_table(Tx::table());
+            let _ = txn.open_table(Unstaking::table());
+            let _ = txn.open_table(ZeroGasWallet::table());
         }
         txn.commit()?;
 
@@ -7,6 +7,16 @@ use crate::redb::{BaseTableDefinition, KvTable};
 
 const PROVERS: BaseTableDefinition = TableDefinition::new("provers");
 
+/// The previous version of upgraded prover, kept for rollback
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PreviousImage {
+    pub tag: String,
+    pub image: String,
+    pub digest: Option<String>,
+    /// the image will be deleted after it
+    pub until: i64,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Prover {
     pub prover: Address,
@@ -17,6 +27,33 @@ pub struct Prover {
     pub ptype: ProverType,
     pub types: String,
     pub created: i64,
+    /// scheduling priority, higher is accepted first
+    #[serde(default)]
+    pub priority: i64,
+    /// max parallel tasks of this prover
+    #[serde(default)]
+    pub parallel: Option<usize>,
+    /// slots cost of one task
+    #[serde(default)]
+    pub weight: Option<usize>,
+    /// the pinned image digest, verified after pull and before run
+    #[serde(default)]
+    pub digest: Option<String>,
+    /// the image mismatched the pinned digest, refuse to run
+    #[serde(default)]
+    pub blocked: Option<String>,
+    /// the registry of image, None is the default proxy or docker hub
+    #[serde(default)]
+    pub registry: Option<String>,
+    /// the organisation of image, None is the default organisation
+    #[serde(default)]
+    pub org: Option<String>,
+    /// the previous version kept for rollback
+    #[serde(default)]
+    pub previous: Option<PreviousImage>,
+    /// the tag rolled back from, not upgrade to it again
+    #[serde(default)]
+    pub rollback: Option<String>,
 }
 
 impl Prover {
@@ -0,0 +1,40 @@
+use ethers::types::Address;
+use redb::TableDefinition;
+use serde::{Deserialize, Serialize};
+
+use crate::redb::{BaseTableDefinition, KvTable};
+
+const PULLED_IMAGES: BaseTableDefinition = TableDefinition::new("pulled_images");
+
+/// The image pulled by miner for prover, only these images are collected by gc
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PulledImage {
+    /// image id
+    pub id: String,
+    pub prover: Address,
+    pub created: i64,
+}
+
+impl PulledImage {
+    pub fn to_key(id: &str) -> &[u8] {
+        id.as_bytes()
+    }
+}
+
+impl KvTable for PulledImage {
+    fn table<'a>() -> BaseTableDefinition<'a> {
+        PULLED_IMAGES
+    }
+
+    fn key(&self) -> Vec<u8> {
+        Self::to_key(&self.id).to_vec()
+    }
+
+    fn to_value(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or(vec![])
+    }
+
+    fn from_value(_key: &[u8], value: &[u8]) -> Option<Self> {
+        serde_json::from_slice(value).ok()
+    }
+}
@@ -0,0 +1,48 @@
+use ethers::types::{Address, U256};
+use redb::TableDefinition;
+use serde::{Deserialize, Serialize};
+
+use crate::redb::{BaseTableDefinition, KvTable};
+
+const REWARDS: BaseTableDefinition = TableDefinition::new("rewards");
+
+/// The labor of miner for a prover in an epoch, and the collected reward
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Reward {
+    pub epoch: u64,
+    pub prover: Address,
+    /// total work from `MinerLabor` events
+    pub work: U256,
+    /// times of labor
+    pub labors: u64,
+    /// collected amount from `MinerCollect` event, None if not collected
+    pub amount: Option<U256>,
+    pub updated: i64,
+}
+
+impl Reward {
+    /// sorted by epoch
+    pub fn to_key(epoch: u64, prover: &Address) -> Vec<u8> {
+        let mut key = epoch.to_be_bytes().to_vec();
+        key.extend(prover.as_bytes());
+        key
+    }
+}
+
+impl KvTable for Reward {
+    fn table<'a>() -> BaseTableDefinition<'a> {
+        REWARDS
+    }
+
+    fn key(&self) -> Vec<u8> {
+        Self::to_key(self.epoch, &self.prover)
+    }
+
+    fn to_value(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or(vec![])
+    }
+
+    fn from_value(_key: &[u8], value: &[u8]) -> Option<Self> {
+        serde_json::from_slice(value).ok()
+    }
+}
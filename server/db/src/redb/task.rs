@@ -15,6 +15,9 @@ pub struct Task {
     pub is_me: bool,
     pub over: bool,
     pub container: String,
+    /// the proof submission in tx queue
+    #[serde(default)]
+    pub tx: Option<u64>,
 }
 
 impl Task {
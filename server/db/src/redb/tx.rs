@@ -0,0 +1,106 @@
+use ethers::types::{Bytes, H256, U256};
+use redb::TableDefinition;
+use serde::{Deserialize, Serialize};
+
+use crate::redb::{BaseTableDefinition, KvTable};
+
+const TXS: BaseTableDefinition = TableDefinition::new("txs");
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TxStatus {
+    /// waiting to send (or resend after failure)
+    Queued,
+    /// sent to chain, waiting for receipt
+    Sent,
+    /// mined and success
+    Mined,
+    /// mined but reverted, or rejected by the node
+    Reverted,
+    /// failed in simulation, never sent
+    Skipped,
+    /// gave up after retries
+    Dropped,
+}
+
+impl TxStatus {
+    /// the tx will never change again
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            TxStatus::Mined | TxStatus::Reverted | TxStatus::Skipped | TxStatus::Dropped
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TxKind {
+    /// accept task, payload is the url
+    Accept,
+    /// submit task proof, payload is the proof
+    Submit,
+    /// submit miner test proof, payload is the proof
+    MinerTest,
+    /// collect miner rewards of an epoch, tid is the epoch
+    Collect,
+    /// claim the unstaked tokens of miner
+    Claim,
+}
+
+/// Transaction in the pool queue, linked to task by tid
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Tx {
+    pub id: u64,
+    pub kind: TxKind,
+    /// task id or miner test id
+    pub tid: u64,
+    pub payload: Bytes,
+    pub status: TxStatus,
+    pub hash: Option<H256>,
+    /// the replaced hashes by fee bumping
+    #[serde(default)]
+    pub replaced: Vec<H256>,
+    /// nonce of controller, None if sent by 0 gas service
+    #[serde(default)]
+    pub nonce: Option<U256>,
+    /// max fee per gas (or gas price of legacy tx)
+    #[serde(default)]
+    pub max_fee: Option<U256>,
+    #[serde(default)]
+    pub priority_fee: Option<U256>,
+    /// the block number when sent
+    #[serde(default)]
+    pub sent_block: u64,
+    /// times of sending
+    pub attempts: u32,
+    /// not send again before this time
+    pub next_try: i64,
+    pub created: i64,
+    pub updated: i64,
+    pub error: Option<String>,
+}
+
+impl Tx {
+    pub fn to_key(id: u64) -> [u8; 8] {
+        id.to_be_bytes()
+    }
+}
+
+impl KvTable for Tx {
+    fn table<'a>() -> BaseTableDefinition<'a> {
+        TXS
+    }
+
+    fn key(&self) -> Vec<u8> {
+        Self::to_key(self.id).to_vec()
+    }
+
+    fn to_value(&self) -> Vec<u8> {
+        serde_json::to_vec(&self).unwrap_or(vec![])
+    }
+
+    fn from_value(_key: &[u8], value: &[u8]) -> Option<Self> {
+        serde_json::from_slice(value).ok()
+    }
+}
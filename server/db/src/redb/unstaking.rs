@@ -0,0 +1,39 @@
+use ethers::types::U256;
+use redb::TableDefinition;
+use serde::{Deserialize, Serialize};
+
+use crate::redb::{BaseTableDefinition, KvTable};
+
+const UNSTAKINGS: BaseTableDefinition = TableDefinition::new("unstakings");
+
+/// The unstaking of miner from `AddUnstaking` events, claimable after the epoch
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Unstaking {
+    pub epoch: u64,
+    pub amount: U256,
+    pub updated: i64,
+}
+
+impl Unstaking {
+    pub fn to_key(epoch: u64) -> [u8; 8] {
+        epoch.to_be_bytes()
+    }
+}
+
+impl KvTable for Unstaking {
+    fn table<'a>() -> BaseTableDefinition<'a> {
+        UNSTAKINGS
+    }
+
+    fn key(&self) -> Vec<u8> {
+        Self::to_key(self.epoch).to_vec()
+    }
+
+    fn to_value(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or(vec![])
+    }
+
+    fn from_value(_key: &[u8], value: &[u8]) -> Option<Self> {
+        serde_json::from_slice(value).ok()
+    }
+}
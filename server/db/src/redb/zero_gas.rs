@@ -0,0 +1,49 @@
+use ethers::types::{Address, U256};
+use redb::TableDefinition;
+use serde::{Deserialize, Serialize};
+
+use crate::redb::{BaseTableDefinition, KvTable};
+
+const ZERO_GAS: BaseTableDefinition = TableDefinition::new("zero_gas");
+
+/// The 0 gas AA wallet of controller, keep the local nonce across restarts
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ZeroGasWallet {
+    pub controller: Address,
+    /// AA wallet of controller
+    pub wallet: Address,
+    /// next nonce of AA wallet
+    pub nonce: u64,
+    /// remaining 0 gas allowance from `/balanceof`
+    pub balance: U256,
+    /// the allowance is lower than threshold
+    pub low: bool,
+    /// the AA wallet can send txs
+    pub working: bool,
+    /// the time of last check
+    pub updated: i64,
+}
+
+impl ZeroGasWallet {
+    pub fn to_key(controller: &Address) -> &[u8] {
+        controller.as_bytes()
+    }
+}
+
+impl KvTable for ZeroGasWallet {
+    fn table<'a>() -> BaseTableDefinition<'a> {
+        ZERO_GAS
+    }
+
+    fn key(&self) -> Vec<u8> {
+        Self::to_key(&self.controller).to_vec()
+    }
+
+    fn to_value(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or(vec![])
+    }
+
+    fn from_value(_key: &[u8], value: &[u8]) -> Option<Self> {
+        serde_json::from_slice(value).ok()
+    }
+}
@@ -2,22 +2,61 @@
 extern crate tracing;
 
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use bollard::{
     container::{
-        Config, CreateContainerOptions, InspectContainerOptions, StartContainerOptions,
+        Config, CreateContainerOptions, InspectContainerOptions, ListContainersOptions,
+        LogsOptions, RemoveContainerOptions, StartContainerOptions, StatsOptions,
         StopContainerOptions,
     },
-    image::{CreateImageOptions, ListImagesOptions},
-    models::{ContainerState, HostConfig},
+    image::{CreateImageOptions, ListImagesOptions, RemoveImageOptions},
+    models::HostConfig,
     Docker,
 };
 use futures_util::StreamExt;
 use pozk_utils::get_task_api;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+pub use bollard::auth::DockerCredentials;
+
+mod runtime;
+pub use runtime::*;
+
+#[cfg(feature = "mock")]
+mod mock;
+#[cfg(feature = "mock")]
+pub use mock::*;
 
 const DOCKER_ORG: &str = "zyphernetwork";
+const DOCKER_HUB: &str = "docker.io";
 const DEFAULT_NETWORK: &str = "pozk"; // it will use in docker-compose
 
+/// credentials of registries, registry host => credentials
+pub type RegistryAuths = HashMap<String, DockerCredentials>;
+
+/// the progress of a layer when pulling image
+#[derive(Debug, Clone, Default)]
+pub struct PullProgress {
+    pub layer: String,
+    pub status: String,
+    pub current: Option<i64>,
+    pub total: Option<i64>,
+}
+
+/// the result of garbage collection
+#[derive(Debug, Clone, Default)]
+pub struct GcReport {
+    /// the removed images
+    pub images: Vec<String>,
+    /// the removed stopped containers
+    pub containers: usize,
+    /// bytes of the removed images
+    pub reclaimed: u64,
+    /// bytes of the remaining pulled images
+    pub size: u64,
+}
+
 #[derive(Default)]
 pub struct RunOption {
     cpu: Option<i64>,
@@ -28,31 +67,68 @@ pub struct RunOption {
 pub struct DockerManager {
     proxy: Option<String>,
     docker: Docker,
+    auths: RegistryAuths,
+    /// the inner API server of tasks for containers
+    server: String,
 }
 
 impl DockerManager {
-    pub fn new(proxy: Option<String>) -> Result<Self> {
+    pub fn new(proxy: Option<String>, auths: RegistryAuths, server: &str) -> Result<Self> {
         let docker = Docker::connect_with_socket_defaults()?;
-        Ok(Self { proxy, docker })
+        Ok(Self {
+            proxy,
+            docker,
+            auths,
+            server: server.to_owned(),
+        })
     }
+}
 
-    /// pull new prover image
-    pub async fn pull(&self, prover: &str, tag: &str) -> Result<String> {
-        let repo_tag = if let Some(proxy) = &self.proxy {
-            format!("{}/{}/{}:{}", proxy, DOCKER_ORG, prover, tag)
-        } else {
-            format!("{}/{}:{}", DOCKER_ORG, prover, tag)
+#[async_trait]
+impl ProverRuntime for DockerManager {
+    /// pull new prover image, from the registry and organisation of prover if set,
+    /// otherwise from the default organisation by proxy or docker hub
+    async fn pull(
+        &self,
+        registry: Option<&str>,
+        org: Option<&str>,
+        prover: &str,
+        tag: &str,
+        progress: &(dyn Fn(PullProgress) + Send + Sync),
+    ) -> Result<String> {
+        let org = org.unwrap_or(DOCKER_ORG);
+        // the registry may be configured as url, e.g. https://host/v2/
+        let (host, repo_tag) = match registry.or(self.proxy.as_deref()) {
+            Some(registry) => {
+                let host = registry_host(registry);
+                let repo_tag = format!("{}/{}/{}:{}", host, org, prover, tag);
+                (host, repo_tag)
+            }
+            None => (DOCKER_HUB.to_owned(), format!("{}/{}:{}", org, prover, tag)),
         };
+        let credentials = self.auths.get(&host).cloned();
 
         let pull_options = CreateImageOptions {
             from_image: repo_tag.clone(),
             ..Default::default()
         };
-        let mut pull_stream = self.docker.create_image(Some(pull_options), None, None);
+        let mut pull_stream = self
+            .docker
+            .create_image(Some(pull_options), None, credentials);
         while let Some(pull_result) = pull_stream.next().await {
             match pull_result {
                 Ok(v) => {
-                    info!("[Docker] pull image: {v:?}");
+                    debug!("[Docker] pull image: {v:?}");
+                    if let Some(e) = v.error {
+                        return Err(anyhow!("pull image repo_tag: {repo_tag}, err: {e}"));
+                    }
+                    let detail = v.progress_detail.unwrap_or_default();
+                    progress(PullProgress {
+                        layer: v.id.unwrap_or_default(),
+                        status: v.status.unwrap_or_default(),
+                        current: detail.current,
+                        total: detail.total,
+                    });
                 }
                 Err(e) => {
                     error!("[Docker] pull image: {e:?}");
@@ -87,7 +163,7 @@ impl DockerManager {
     }
 
     /// start a container to run the zkp
-    pub async fn run(
+    async fn run(
         &self,
         image: &str,
         tid: &str,
@@ -96,7 +172,7 @@ impl DockerManager {
         roption: RunOption,
     ) -> Result<String> {
         let name = format!("{}-{}", image, tid);
-        let input_env = format!("INPUT={}", get_task_api(tid));
+        let input_env = format!("INPUT={}", get_task_api(&self.server, tid));
         let zkvm_env = format!("ZKVM={}", zkvm);
         let overtime_env = format!("OVERTIME={}", overtime);
 
@@ -141,8 +217,25 @@ impl DockerManager {
         Ok(container_id)
     }
 
+    /// the digests of image: the image id, then the repo digests from registries
+    async fn digests(&self, image: &str) -> Result<Vec<String>> {
+        let info = self.docker.inspect_image(image).await?;
+
+        let mut digests = vec![];
+        if let Some(id) = info.id {
+            digests.push(image_id(&id));
+        }
+        for repo_digest in info.repo_digests.unwrap_or_default() {
+            if let Some((_, digest)) = repo_digest.split_once('@') {
+                digests.push(image_id(digest));
+            }
+        }
+
+        Ok(digests)
+    }
+
     /// list all images
-    pub async fn list(&self) -> Result<HashMap<String, String>> {
+    async fn list(&self) -> Result<HashMap<String, String>> {
         let data = self
             .docker
             .list_images::<String>(None)
@@ -155,7 +248,7 @@ impl DockerManager {
     }
 
     /// remove image
-    pub async fn remove(&self, image: &str) -> Result<()> {
+    async fn remove(&self, image: &str) -> Result<()> {
         let remove_list = self.docker.remove_image(image, None, None).await?;
 
         info!("[Docker] remove image: {remove_list:?}");
@@ -164,15 +257,138 @@ impl DockerManager {
     }
 
     /// container status
-    pub async fn status(&self, container: &str) -> Result<Option<ContainerState>> {
+    async fn status(&self, container: &str) -> Result<Option<ContainerStatus>> {
         let op = Some(InspectContainerOptions { size: false });
 
         let container = self.docker.inspect_container(container, op).await?;
-        Ok(container.state)
+        Ok(container.state.map(|state| ContainerStatus {
+            running: state.running.unwrap_or(false),
+            exit_code: state.exit_code,
+        }))
+    }
+
+    /// the last lines of container logs, stdout and stderr
+    async fn logs(&self, container: &str, tail: usize) -> Result<Vec<String>> {
+        let op = Some(LogsOptions {
+            stdout: true,
+            stderr: true,
+            tail: tail.to_string(),
+            ..Default::default()
+        });
+
+        let mut lines = vec![];
+        let mut stream = self.docker.logs(container, op);
+        while let Some(output) = stream.next().await {
+            lines.push(output?.to_string().trim_end().to_owned());
+        }
+
+        Ok(lines)
+    }
+
+    /// watch the container until it exits, return the peak memory usage (bytes)
+    async fn peak_memory(&self, container: &str) -> Result<u64> {
+        let op = Some(StatsOptions {
+            stream: true,
+            one_shot: false,
+        });
+
+        let mut peak = 0;
+        let mut stream = self.docker.stats(container, op);
+        while let Some(stats) = stream.next().await {
+            let Ok(stats) = stats else {
+                break;
+            };
+            let memory = &stats.memory_stats;
+            let usage = memory.max_usage.max(memory.usage).unwrap_or(0);
+            peak = peak.max(usage);
+        }
+
+        Ok(peak)
+    }
+
+    /// remove the stopped containers and the pulled images which not kept,
+    /// the images not pulled for provers are never touched
+    async fn gc(&self, pulled: &HashSet<String>, keep: &HashSet<String>) -> Result<GcReport> {
+        let images: Vec<_> = self
+            .docker
+            .list_images::<String>(None)
+            .await?
+            .into_iter()
+            .filter(|i| pulled.contains(&image_id(&i.id)))
+            .collect();
+        let ids: HashSet<String> = images.iter().map(|i| image_id(&i.id)).collect();
+
+        let mut report = GcReport::default();
+
+        // the containers of images, auto removed when exits, except created or dead
+        let mut filters = HashMap::new();
+        filters.insert("status", vec!["created", "exited", "dead"]);
+        let op = ListContainersOptions {
+            all: true,
+            filters,
+            ..Default::default()
+        };
+        for c in self.docker.list_containers(Some(op)).await? {
+            let (Some(id), Some(image)) = (c.id, c.image_id) else {
+                continue;
+            };
+            if !ids.contains(&image_id(&image)) {
+                continue;
+            }
+            let op = Some(RemoveContainerOptions {
+                force: false,
+                ..Default::default()
+            });
+            match self.docker.remove_container(&id, op).await {
+                Ok(_) => report.containers += 1,
+                Err(e) => warn!("[Docker] remove container {}: {}", id, e),
+            }
+        }
+
+        for image in images {
+            let id = image_id(&image.id);
+            let size = image.size.max(0) as u64;
+            if keep.contains(&id) {
+                report.size += size;
+                continue;
+            }
+
+            // remove by tags, the image is deleted with the last tag
+            let mut names: Vec<&str> = image
+                .repo_tags
+                .iter()
+                .filter(|t| t.as_str() != "<none>:<none>")
+                .map(|t| t.as_str())
+                .collect();
+            if names.is_empty() {
+                names.push(&id);
+            }
+
+            let mut removed = true;
+            for name in names {
+                let op = Some(RemoveImageOptions {
+                    force: false,
+                    noprune: false,
+                });
+                if let Err(e) = self.docker.remove_image(name, op, None).await {
+                    warn!("[Docker] remove image {}: {}", name, e);
+                    removed = false;
+                }
+            }
+            if removed {
+                info!("[Docker] gc image: {}", id);
+                report.reclaimed += size;
+                report.images.push(id);
+            } else {
+                report.size += size;
+            }
+        }
+
+        Ok(report)
     }
 
     /// stop container
-    pub async fn stop(&self, container: &str) -> Result<()> {
+    async fn stop(&self, container: &str) -> Result<()> {
         let op = Some(StopContainerOptions { t: 30 });
 
         self.docker.stop_container(container, op).await?;
@@ -180,8 +396,66 @@ impl DockerManager {
     }
 }
 
-#[inline]
-fn image_id(id: &str) -> String {
+/// the credentials of registry by username and password (or access token)
+pub fn registry_auth(host: &str, username: &str, password: &str) -> (String, DockerCredentials) {
+    let host = registry_host(host);
+    let credentials = DockerCredentials {
+        username: Some(username.to_owned()),
+        password: Some(password.to_owned()),
+        serveraddress: Some(host.clone()),
+        ..Default::default()
+    };
+    (host, credentials)
+}
+
+/// load the registry credentials from docker config.json
+pub fn load_docker_config(path: &str) -> Result<RegistryAuths> {
+    let content = std::fs::read_to_string(path)?;
+    let config: serde_json::Value = serde_json::from_str(&content)?;
+
+    let mut auths = RegistryAuths::new();
+    let Some(items) = config["auths"].as_object() else {
+        return Ok(auths);
+    };
+    for (host, item) in items {
+        if let Some(token) = item["identitytoken"].as_str() {
+            let credentials = DockerCredentials {
+                identitytoken: Some(token.to_owned()),
+                serveraddress: Some(registry_host(host)),
+                ..Default::default()
+            };
+            auths.insert(registry_host(host), credentials);
+            continue;
+        }
+
+        let Some(auth) = item["auth"].as_str() else {
+            continue;
+        };
+        let decoded = String::from_utf8(STANDARD.decode(auth)?)?;
+        if let Some((username, password)) = decoded.split_once(':') {
+            let (host, credentials) = registry_auth(host, username, password);
+            auths.insert(host, credentials);
+        }
+    }
+
+    Ok(auths)
+}
+
+/// the host of registry, docker hub in many names
+pub(crate) fn registry_host(registry: &str) -> String {
+    let host = registry
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .split('/')
+        .next()
+        .unwrap_or("");
+    match host {
+        "index.docker.io" | "registry-1.docker.io" => DOCKER_HUB.to_owned(),
+        _ => host.to_owned(),
+    }
+}
+
+pub(crate) fn image_id(id: &str) -> String {
     let split = id.split(":").collect::<Vec<_>>();
     if split.len() < 2 {
         id.to_owned()
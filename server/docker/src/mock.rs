@@ -0,0 +1,294 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use pozk_utils::{download_input_with_uri, get_task_api, parse_task_input, upload_proof_with_uri};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::sleep;
+
+use crate::{
+    registry_host, ContainerStatus, GcReport, ProverRuntime, PullProgress, RunOption, DOCKER_ORG,
+};
+
+/// inputs, publics => output, proof. None means the prover crashed without proof
+pub type MockProver = Arc<dyn Fn(Vec<u8>, Vec<u8>) -> Option<(Vec<u8>, Vec<u8>)> + Send + Sync>;
+
+struct MockImage {
+    repo_tag: String,
+    digest: String,
+}
+
+struct MockContainer {
+    image: String,
+    status: ContainerStatus,
+    logs: Vec<String>,
+}
+
+#[derive(Default)]
+struct MockState {
+    /// image id => image
+    images: HashMap<String, MockImage>,
+    /// container name => container
+    containers: HashMap<String, MockContainer>,
+}
+
+/// In-process stand-in of the docker runtime, the containers are tasks which download
+/// the inputs and upload the proof by `/inner/tasks/:tid` like the real provers.
+#[derive(Clone)]
+pub struct MockRuntime {
+    state: Arc<Mutex<MockState>>,
+    prover: Arc<Mutex<MockProver>>,
+    /// the proving time of containers
+    delay: Arc<Mutex<Duration>>,
+    /// the inner API server of tasks
+    server: String,
+}
+
+impl MockRuntime {
+    /// the default prover outputs the publics, and proves with the inputs
+    pub fn new(server: &str) -> Self {
+        let prover: MockProver = Arc::new(|inputs, publics| Some((publics, inputs)));
+        Self {
+            state: Arc::new(Mutex::new(MockState::default())),
+            prover: Arc::new(Mutex::new(prover)),
+            delay: Arc::new(Mutex::new(Duration::ZERO)),
+            server: server.to_owned(),
+        }
+    }
+
+    /// change how the containers prove
+    pub fn set_prover(&self, prover: MockProver) {
+        *self.prover.lock().unwrap() = prover;
+    }
+
+    /// change the proving time of containers
+    pub fn set_delay(&self, delay: Duration) {
+        *self.delay.lock().unwrap() = delay;
+    }
+
+    /// the names of containers, running or exited
+    pub fn containers(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state.containers.keys().cloned().collect()
+    }
+
+    fn exit(&self, name: &str, code: i64, log: String) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(c) = state.containers.get_mut(name) {
+            if c.status.running {
+                c.status = ContainerStatus {
+                    running: false,
+                    exit_code: Some(code),
+                };
+            }
+            c.logs.push(log);
+        }
+    }
+}
+
+fn hash_id(value: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+#[async_trait]
+impl ProverRuntime for MockRuntime {
+    async fn pull(
+        &self,
+        registry: Option<&str>,
+        org: Option<&str>,
+        prover: &str,
+        tag: &str,
+        progress: &(dyn Fn(PullProgress) + Send + Sync),
+    ) -> Result<String> {
+        let org = org.unwrap_or(DOCKER_ORG);
+        let repo_tag = match registry {
+            Some(registry) => format!("{}/{}/{}:{}", registry_host(registry), org, prover, tag),
+            None => format!("{}/{}:{}", org, prover, tag),
+        };
+        let id = hash_id(&repo_tag);
+
+        progress(PullProgress {
+            layer: id.clone(),
+            status: "Pull complete".to_owned(),
+            current: Some(1),
+            total: Some(1),
+        });
+
+        let digest = hash_id(&format!("manifest-{}", repo_tag));
+        let mut state = self.state.lock().unwrap();
+        state
+            .images
+            .insert(id.clone(), MockImage { repo_tag, digest });
+        Ok(id)
+    }
+
+    async fn run(
+        &self,
+        image: &str,
+        tid: &str,
+        _zkvm: &str,
+        _overtime: i64,
+        _roption: RunOption,
+    ) -> Result<String> {
+        let name = format!("{}-{}", image, tid);
+        {
+            let mut state = self.state.lock().unwrap();
+            if !state.images.contains_key(image) {
+                return Err(anyhow!("No such image: {}", image));
+            }
+            if state
+                .containers
+                .get(&name)
+                .map(|c| c.status.running)
+                .unwrap_or(false)
+            {
+                return Err(anyhow!("Conflict container: {}", name));
+            }
+            state.containers.insert(
+                name.clone(),
+                MockContainer {
+                    image: image.to_owned(),
+                    status: ContainerStatus {
+                        running: true,
+                        exit_code: None,
+                    },
+                    logs: vec![],
+                },
+            );
+        }
+
+        let runtime = self.clone();
+        let container = name.clone();
+        let uri = get_task_api(&self.server, tid);
+        let prover = self.prover.lock().unwrap().clone();
+        let delay = *self.delay.lock().unwrap();
+        tokio::spawn(async move {
+            let res = async {
+                let data = download_input_with_uri(&uri).await?;
+                let (inputs, publics) = parse_task_input(data).await?;
+                sleep(delay).await;
+                let (output, proof) = prover(inputs, publics).ok_or(anyhow!("prove failed"))?;
+
+                // stopped when proving
+                let running = matches!(runtime.status(&container).await, Ok(Some(s)) if s.running);
+                if !running {
+                    return Err(anyhow!("stopped"));
+                }
+                upload_proof_with_uri(&uri, output, proof).await
+            }
+            .await;
+
+            match res {
+                Ok(()) => runtime.exit(&container, 0, "proof uploaded".to_owned()),
+                Err(e) => runtime.exit(&container, 1, e.to_string()),
+            }
+        });
+
+        Ok(name)
+    }
+
+    async fn stop(&self, container: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let c = state
+            .containers
+            .get_mut(container)
+            .ok_or(anyhow!("No such container: {}", container))?;
+        c.status = ContainerStatus {
+            running: false,
+            exit_code: Some(137),
+        };
+        Ok(())
+    }
+
+    async fn status(&self, container: &str) -> Result<Option<ContainerStatus>> {
+        let state = self.state.lock().unwrap();
+        let c = state
+            .containers
+            .get(container)
+            .ok_or(anyhow!("No such container: {}", container))?;
+        Ok(Some(c.status))
+    }
+
+    async fn list(&self) -> Result<HashMap<String, String>> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .images
+            .iter()
+            .map(|(id, image)| (id.clone(), image.repo_tag.clone()))
+            .collect())
+    }
+
+    async fn remove(&self, image: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let id = state
+            .images
+            .iter()
+            .find(|(id, i)| *id == image || i.repo_tag == image)
+            .map(|(id, _)| id.clone())
+            .ok_or(anyhow!("No such image: {}", image))?;
+        if state
+            .containers
+            .values()
+            .any(|c| c.image == id && c.status.running)
+        {
+            return Err(anyhow!("Image is being used: {}", image));
+        }
+        state.images.remove(&id);
+        Ok(())
+    }
+
+    async fn logs(&self, container: &str, tail: usize) -> Result<Vec<String>> {
+        let state = self.state.lock().unwrap();
+        let c = state
+            .containers
+            .get(container)
+            .ok_or(anyhow!("No such container: {}", container))?;
+        let skip = c.logs.len().saturating_sub(tail);
+        Ok(c.logs[skip..].to_vec())
+    }
+
+    async fn digests(&self, image: &str) -> Result<Vec<String>> {
+        let state = self.state.lock().unwrap();
+        let i = state
+            .images
+            .get(image)
+            .ok_or(anyhow!("No such image: {}", image))?;
+        Ok(vec![image.to_owned(), i.digest.clone()])
+    }
+
+    async fn peak_memory(&self, container: &str) -> Result<u64> {
+        while matches!(self.status(container).await, Ok(Some(s)) if s.running) {
+            sleep(Duration::from_millis(50)).await;
+        }
+        Ok(0)
+    }
+
+    async fn gc(&self, pulled: &HashSet<String>, keep: &HashSet<String>) -> Result<GcReport> {
+        let mut state = self.state.lock().unwrap();
+        let mut report = GcReport::default();
+
+        let before = state.containers.len();
+        // the stopped containers of pulled images, as the docker runtime
+        state
+            .containers
+            .retain(|_, c| c.status.running || !pulled.contains(&c.image));
+        report.containers = before - state.containers.len();
+
+        let images: Vec<String> = state
+            .images
+            .keys()
+            .filter(|id| pulled.contains(*id) && !keep.contains(*id))
+            .cloned()
+            .collect();
+        for id in images {
+            state.images.remove(&id);
+            report.images.push(id);
+        }
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+
+use crate::{GcReport, PullProgress, RunOption};
+
+/// the status of container
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContainerStatus {
+    pub running: bool,
+    /// the exit code when container exited
+    pub exit_code: Option<i64>,
+}
+
+/// The runtime of prover images. The container named `{image}-{tid}` downloads the
+/// inputs and uploads the proof by `/inner/tasks/:tid`.
+#[async_trait]
+pub trait ProverRuntime: Send + Sync {
+    /// pull the prover image, return the image id
+    async fn pull(
+        &self,
+        registry: Option<&str>,
+        org: Option<&str>,
+        prover: &str,
+        tag: &str,
+        progress: &(dyn Fn(PullProgress) + Send + Sync),
+    ) -> Result<String>;
+
+    /// start a container to run the task, return the container id
+    async fn run(
+        &self,
+        image: &str,
+        tid: &str,
+        zkvm: &str,
+        overtime: i64,
+        roption: RunOption,
+    ) -> Result<String>;
+
+    /// stop the container
+    async fn stop(&self, container: &str) -> Result<()>;
+
+    /// the container status
+    async fn status(&self, container: &str) -> Result<Option<ContainerStatus>>;
+
+    /// all images, image id => repo tags
+    async fn list(&self) -> Result<HashMap<String, String>>;
+
+    /// remove the image
+    async fn remove(&self, image: &str) -> Result<()>;
+
+    /// the last lines of container logs
+    async fn logs(&self, container: &str, tail: usize) -> Result<Vec<String>>;
+
+    /// the digests of image: the image id, then the repo digests from registries
+    async fn digests(&self, image: &str) -> Result<Vec<String>>;
+
+    /// watch the container until it exits, return the peak memory usage (bytes)
+    async fn peak_memory(&self, container: &str) -> Result<u64>;
+
+    /// remove the stopped containers and the pulled images which not kept
+    async fn gc(&self, pulled: &HashSet<String>, keep: &HashSet<String>) -> Result<GcReport>;
+}
@@ -1,10 +1,11 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Args;
 use ethers::prelude::Address;
 use pozk_utils::contract_address;
 use serde::Deserialize;
 
 #[derive(Args, Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct MonitorConfig {
     #[clap(long, help = "`monitor`: network type, localhost|testnet|mainnet")]
     pub network: String,
@@ -38,6 +39,20 @@ pub struct MonitorConfig {
     #[clap(long, help = "`monitor`: add 0 gas service", default_value = "")]
     pub zero_gas: String,
 
+    #[clap(
+        long,
+        help = "`monitor`: 0 gas policy, only|prefer|paid, `only` never pays gas, `paid` never uses 0 gas",
+        default_value = "prefer"
+    )]
+    pub zero_gas_policy: String,
+
+    #[clap(
+        long,
+        help = "`monitor`: alert when 0 gas allowance lower than it (in wei), e.g. 10000000000000000",
+        default_value = "10000000000000000"
+    )]
+    pub zero_gas_low_balance: u128,
+
     #[clap(
         long,
         help = "`monitor`: monitor start height (Optional), e.g. 34736669"
@@ -55,6 +70,64 @@ pub struct MonitorConfig {
 
     #[clap(long, help = "`monitor`: special controller contract (Optional)")]
     pub controller_address: Option<String>,
+
+    #[clap(long, help = "`monitor`: special reward contract (Optional)")]
+    pub reward_address: Option<String>,
+
+    #[clap(long, help = "`monitor`: special epoch contract (Optional)")]
+    pub epoch_address: Option<String>,
+
+    #[clap(
+        long,
+        help = "`monitor`: max fee per gas in wei (Optional), no cap by default, e.g. 2000000000"
+    )]
+    pub max_fee: Option<u64>,
+
+    #[clap(
+        long,
+        help = "`monitor`: priority fee per gas in wei (Optional), default 1000000"
+    )]
+    pub priority_fee: Option<u64>,
+
+    #[clap(
+        long,
+        help = "`monitor`: replace tx with higher fee if not mined in these blocks, e.g. 5",
+        default_value = "5"
+    )]
+    pub bump_blocks: u64,
+
+    #[clap(long, help = "`monitor`: auto collect the rewards of finished epochs")]
+    pub auto_collect: bool,
+
+    #[clap(
+        long,
+        help = "`monitor`: min estimated reward (in wei) to auto collect an epoch, e.g. 1000000000000000000",
+        default_value = "0"
+    )]
+    pub collect_min_reward: u128,
+
+    #[clap(
+        long,
+        help = "`monitor`: reward token per gas token, the reward must also cover gas cost * ratio, e.g. 1000",
+        default_value = "0"
+    )]
+    pub collect_gas_ratio: u128,
+
+    /// sync the approved provers at startup, set by the auto install policy
+    #[clap(skip)]
+    #[serde(skip)]
+    pub sync_provers: bool,
+}
+
+/// How the pool pays for txs when 0 gas service is configured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZeroGasPolicy {
+    /// only send by 0 gas, txs wait when the service is down
+    Only,
+    /// send by 0 gas, fallback to pay gas when failure
+    Prefer,
+    /// always pay gas
+    Paid,
 }
 
 impl Default for MonitorConfig {
@@ -71,6 +144,17 @@ impl Default for MonitorConfig {
             stake_address: None,
             controller_address: None,
             zero_gas: "".to_owned(),
+            zero_gas_policy: "prefer".to_owned(),
+            zero_gas_low_balance: 10000000000000000,
+            max_fee: None,
+            priority_fee: None,
+            bump_blocks: 5,
+            reward_address: None,
+            epoch_address: None,
+            auto_collect: false,
+            collect_min_reward: 0,
+            collect_gas_ratio: 0,
+            sync_provers: false,
         }
     }
 }
@@ -80,6 +164,15 @@ impl MonitorConfig {
         self.endpoints.split(";").map(|x| x.to_owned()).collect()
     }
 
+    pub fn zero_gas_policy(&self) -> Result<ZeroGasPolicy> {
+        match self.zero_gas_policy.as_str() {
+            "only" => Ok(ZeroGasPolicy::Only),
+            "prefer" => Ok(ZeroGasPolicy::Prefer),
+            "paid" => Ok(ZeroGasPolicy::Paid),
+            p => Err(anyhow!("Invalid 0 gas policy: {}", p)),
+        }
+    }
+
     pub fn miner(&self) -> Result<Address> {
         let miner: Address = self.miner.parse()?;
         Ok(miner)
@@ -148,4 +241,36 @@ impl MonitorConfig {
             }
         }
     }
+
+    pub fn reward_address(&self) -> Result<(Address, Option<u64>)> {
+        if let Some(t) = &self.reward_address {
+            let a: Address = t.parse()?;
+
+            Ok((a, self.from))
+        } else {
+            let (a, f) = contract_address(&self.network, "Reward")?;
+
+            if self.from.is_none() {
+                Ok((a, Some(f)))
+            } else {
+                Ok((a, self.from))
+            }
+        }
+    }
+
+    pub fn epoch_address(&self) -> Result<(Address, Option<u64>)> {
+        if let Some(t) = &self.epoch_address {
+            let a: Address = t.parse()?;
+
+            Ok((a, self.from))
+        } else {
+            let (a, f) = contract_address(&self.network, "Epoch")?;
+
+            if self.from.is_none() {
+                Ok((a, Some(f)))
+            } else {
+                Ok((a, self.from))
+            }
+        }
+    }
 }
@@ -2,10 +2,14 @@
 extern crate tracing;
 
 mod config;
-pub use config::MonitorConfig;
+pub use config::{MonitorConfig, ZeroGasPolicy};
 
 mod scan;
 pub use scan::Scan;
 
+mod nonce;
+mod reward;
+mod zero_gas;
+
 mod pool;
 pub use pool::{Pool, PoolMessage};
@@ -0,0 +1,116 @@
+use ethers::prelude::U256;
+use std::collections::BTreeSet;
+
+/// Local nonces of controller. The nonce of a tx which not sent is released and taken
+/// again first, so the later txs never wait for a gap.
+#[derive(Default)]
+pub struct Nonces {
+    /// the next new nonce, None will sync with chain
+    next: Option<U256>,
+    /// the released nonces lower than next
+    released: BTreeSet<U256>,
+}
+
+impl Nonces {
+    /// need sync with chain before taking
+    pub fn synced(&self) -> bool {
+        self.next.is_some()
+    }
+
+    /// sync with the pending nonce of chain, the nonces of txs in flight are kept,
+    /// and the gaps between them are released
+    pub fn sync(&mut self, pending: U256, in_flight: &BTreeSet<U256>) {
+        let next = in_flight
+            .last()
+            .map(|n| *n + 1)
+            .unwrap_or_default()
+            .max(pending);
+
+        self.released.clear();
+        let mut nonce = pending;
+        while nonce < next {
+            if !in_flight.contains(&nonce) {
+                self.released.insert(nonce);
+            }
+            nonce += U256::one();
+        }
+        self.next = Some(next);
+    }
+
+    /// take the lowest released nonce or a new one, None if not synced
+    pub fn take(&mut self) -> Option<U256> {
+        if let Some(nonce) = self.released.pop_first() {
+            return Some(nonce);
+        }
+        let nonce = self.next?;
+        self.next = Some(nonce + 1);
+        Some(nonce)
+    }
+
+    /// the nonce not used by chain, take it again
+    pub fn release(&mut self, nonce: U256) {
+        let Some(mut next) = self.next else {
+            return;
+        };
+        if nonce >= next {
+            return;
+        }
+
+        self.released.insert(nonce);
+        // shrink the tail
+        while next > U256::zero() && self.released.remove(&(next - 1)) {
+            next -= U256::one();
+        }
+        self.next = Some(next);
+    }
+
+    /// sync with chain again at next taking, e.g. a sending failed without knowing
+    /// the nonce is used or not
+    pub fn reset(&mut self) {
+        self.next = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonces() {
+        let n = U256::from;
+        let mut nonces = Nonces::default();
+        assert!(nonces.take().is_none());
+
+        nonces.sync(n(5), &BTreeSet::new());
+        assert_eq!(nonces.take(), Some(n(5)));
+        assert_eq!(nonces.take(), Some(n(6)));
+        assert_eq!(nonces.take(), Some(n(7)));
+
+        // the gap is filled first
+        nonces.release(n(6));
+        assert_eq!(nonces.take(), Some(n(6)));
+        assert_eq!(nonces.take(), Some(n(8)));
+
+        // the tail is not a gap
+        nonces.release(n(8));
+        nonces.release(n(7));
+        assert_eq!(nonces.take(), Some(n(7)));
+        assert_eq!(nonces.take(), Some(n(8)));
+
+        // unknown nonces are ignored
+        nonces.release(n(100));
+        assert_eq!(nonces.take(), Some(n(9)));
+
+        // resync, 5 and 7 still in flight, 6 is free
+        nonces.reset();
+        assert!(nonces.take().is_none());
+        nonces.sync(n(5), &BTreeSet::from([n(5), n(7)]));
+        assert_eq!(nonces.take(), Some(n(6)));
+        assert_eq!(nonces.take(), Some(n(8)));
+
+        // chain is ahead of the txs in flight
+        nonces.reset();
+        nonces.sync(n(20), &BTreeSet::from([n(5), n(7)]));
+        assert_eq!(nonces.take(), Some(n(20)));
+    }
+}
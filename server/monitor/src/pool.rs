@@ -1,26 +1,81 @@
 use anyhow::{anyhow, Result};
-use async_recursion::async_recursion;
-use ethers::prelude::*;
+use chrono::Utc;
+use ethers::{prelude::*, types::transaction::eip2718::TypedTransaction};
+use pozk_db::{ReDB, Reward as RewardRow, Tx, TxKind, TxStatus};
+use pozk_utils::reward::Reward;
 use pozk_utils::{
-    check_zero_gas, create_zero_gas, new_providers, new_signer, pozk_gas_price, zero_gas, AAWallet,
-    Controller, DefaultProvider, DefaultSigner, Stake, Task,
+    new_providers, new_signer, pozk_gas_price, pozk_priority_fee, revert_reason, Controller,
+    DefaultProvider, DefaultSigner, Epoch, HttpZeroGas, ServiceMessage, Stake, Task, ZeroGasClient,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
 };
-use std::{sync::Arc, time::Duration};
 use tokio::{
     select,
-    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
     time::interval,
 };
 
+use crate::nonce::Nonces;
+use crate::reward::{collectable, estimate, rates, Collect};
+use crate::zero_gas::{send_zero_gas, Resend, ZeroGas, ZeroGasResult};
 use crate::MonitorConfig;
 
 const GAS_PRICE: u64 = 1_000_000; // 0.001 GWEI
 
+/// max times to send a tx
+const MAX_ATTEMPTS: u32 = 5;
+
+/// first retry delay (seconds), doubled each time
+const RETRY_BASE: i64 = 5;
+
+/// max retry delay (seconds)
+const RETRY_MAX: i64 = 300;
+
+/// the sent tx not found in chain after this time (seconds) is dropped
+const DROP_TIMEOUT: i64 = 180;
+
+/// max submits in flight (sending or waiting receipt)
+const MAX_IN_FLIGHT: usize = 16;
+
+/// bump fees by this percent when replacing tx
+const FEE_BUMP: u64 = 20;
+
+/// fees of a tx, legacy tx use the max fee as gas price
+#[derive(Clone, Copy, Debug)]
+struct Fees {
+    max_fee: U256,
+    priority_fee: U256,
+}
+
+enum SendResult {
+    /// simulation passed, ready to send, paid or by 0 gas
+    Simulated(bool),
+    Sent(H256),
+    /// simulation failed, not sent
+    Skipped(String),
+    Reverted(String),
+    Failed(String),
+    /// rejected by chain when sent by 0 gas with the nonce, maybe wrong nonce
+    ZeroGasRejected(u64),
+    /// 0 gas service failed with the nonce
+    ZeroGasFailed(u64),
+}
+
 pub enum PoolMessage {
     ChangeController(LocalWallet),
     AcceptTask(u64, String),
     SubmitTask(u64, Vec<u8>),
     SubmitMinerTest(u64, Vec<u8>),
+    /// reply when the submits of accepted tasks are finished
+    Flush(oneshot::Sender<()>),
+    /// claim the unstaked tokens of miner
+    Claim,
 }
 
 pub struct Pool {
@@ -28,23 +83,60 @@ pub struct Pool {
     provider: Arc<DefaultProvider>,
     task: Task<DefaultSigner>,
     stake: Stake<DefaultSigner>,
+    reward: Reward<DefaultSigner>,
+    epoch: Epoch<DefaultProvider>,
+    /// auto collect rewards, None if disabled
+    collect: Option<Collect>,
     controller: Controller<DefaultProvider>,
     miner: Address,
     chain: u64,
     gas_price: Option<U256>,
-    zero_gas: String,
-    zero_gas_working: bool,
-    zero_gas_wallet: AAWallet<DefaultSigner>,
-    zero_gas_nonce: u64,
+    zero_gas: ZeroGas,
+    db: Arc<ReDB>,
+    /// report failed txs to main service
+    sender: UnboundedSender<ServiceMessage>,
+    /// queued and sent txs, id => tx
+    txs: BTreeMap<u64, Tx>,
+    next_tx: u64,
+    /// reply when all txs finished
+    flushing: Vec<oneshot::Sender<()>>,
+    /// local nonces of controller
+    nonces: Nonces,
+    /// txs are sending in background
+    sending: HashSet<u64>,
+    results: UnboundedSender<(u64, SendResult)>,
+    results_receiver: Option<UnboundedReceiver<(u64, SendResult)>>,
+    /// chain support EIP-1559 fees
+    eip1559: bool,
+    /// max fee per gas
+    fee_cap: Option<U256>,
+    priority_fee: U256,
+    /// replace the tx if not mined in these blocks
+    bump_blocks: u64,
+    /// latest block number
+    block: u64,
+    /// latest base fee (EIP-1559) or gas price (legacy)
+    network_fee: Option<U256>,
+    /// txs are replacing with new fees in background
+    replacing: HashMap<u64, Fees>,
 }
 
 enum InnerFuture {
     Message(PoolMessage),
     ZeroGas,
+    Collect,
+    Txs,
+    Sent(u64, SendResult),
 }
 
 impl Pool {
-    pub async fn new(cfg: &MonitorConfig, wallet: LocalWallet, ready: bool) -> Result<Self> {
+    pub async fn new(
+        cfg: &MonitorConfig,
+        wallet: LocalWallet,
+        ready: bool,
+        db: Arc<ReDB>,
+        sender: UnboundedSender<ServiceMessage>,
+    ) -> Result<Self> {
         let wallet_address = wallet.address();
         let providers = new_providers(&cfg.endpoints());
         if providers.is_empty() {
@@ -56,32 +148,98 @@ impl Pool {
         let (task_address, _start) = cfg.task_address()?;
         let (stake_address, _start) = cfg.stake_address()?;
         let (controller_address, _start) = cfg.controller_address()?;
+        let (reward_address, _start) = cfg.reward_address()?;
+        let (epoch_address, _start) = cfg.epoch_address()?;
 
         let signer = new_signer(provider.clone(), wallet.clone()).await?;
         let task = Task::new(task_address, signer.clone());
         let stake = Stake::new(stake_address, signer.clone());
+        let reward = Reward::new(reward_address, signer.clone());
+        let epoch = Epoch::new(epoch_address, provider.clone());
+        let collect = cfg.auto_collect.then(|| Collect {
+            min_reward: U256::from(cfg.collect_min_reward),
+            gas_ratio: U256::from(cfg.collect_gas_ratio),
+        });
         let controller = Controller::new(controller_address, provider.clone());
 
         let miner = cfg.miner()?;
-        let zero_gas = cfg.zero_gas.clone();
-        let zero_gas_wallet = AAWallet::new(Address::zero(), signer);
+        let zero_gas_policy = cfg.zero_gas_policy()?;
+        let zero_gas_client: Option<Arc<dyn ZeroGasClient>> = if cfg.zero_gas.is_empty() {
+            None
+        } else {
+            Some(Arc::new(HttpZeroGas::new(&cfg.zero_gas, provider.clone())))
+        };
+        let zero_gas = ZeroGas::new(
+            zero_gas_client,
+            zero_gas_policy,
+            U256::from(cfg.zero_gas_low_balance),
+            db.clone(),
+        );
+        info!("[Pool] 0 gas policy: {:?}", zero_gas_policy);
 
         let gas_price = pozk_gas_price(&cfg.network);
+        // no fee cap by default, the fees follow the network
+        let fee_cap = cfg.max_fee.map(U256::from);
+        let priority_fee = cfg
+            .priority_fee
+            .map(U256::from)
+            .unwrap_or(pozk_priority_fee());
+
+        // fixed gas price network use legacy tx
+        let eip1559 = gas_price.is_none()
+            && provider
+                .get_block(BlockNumber::Latest)
+                .await?
+                .map(|b| b.base_fee_per_gas.is_some())
+                .unwrap_or(false);
+        info!("[Pool] EIP-1559: {}, fee cap: {:?}", eip1559, fee_cap);
+
+        let (results, results_receiver) = unbounded_channel();
+
+        // load the unfinished txs
+        let (items, _) = db.list::<Tx>(0, db.count::<Tx>()?)?;
+        let next_tx = items.last().map(|t| t.id + 1).unwrap_or(0);
+        let mut txs = BTreeMap::new();
+        for tx in items {
+            if !tx.status.is_final() {
+                txs.insert(tx.id, tx);
+            }
+        }
+        if !txs.is_empty() {
+            info!("[Pool] loaded {} unfinished txs", txs.len());
+        }
 
         let mut pool = Self {
             wallet,
             provider,
             task,
             stake,
+            reward,
+            epoch,
+            collect,
             miner,
             controller,
             chain,
             gas_price,
             zero_gas,
-            zero_gas_wallet,
-            zero_gas_working: false,
-            zero_gas_nonce: 0,
+            db,
+            sender,
+            txs,
+            next_tx,
+            flushing: vec![],
+            nonces: Nonces::default(),
+            sending: HashSet::new(),
+            results,
+            results_receiver: Some(results_receiver),
+            eip1559,
+            fee_cap,
+            priority_fee,
+            bump_blocks: cfg.bump_blocks,
+            block: 0,
+            network_fee: None,
+            replacing: HashMap::new(),
         };
+        pool.refresh_fee().await;
 
         if ready {
             info!("[Pool] set controller to: {}", wallet_address);
@@ -98,7 +256,10 @@ impl Pool {
     }
 
     async fn listen(mut self, mut recv: UnboundedReceiver<PoolMessage>) {
-        let mut gas_interval = interval(Duration::from_secs(600)); // 10min
+        let mut gas_interval = interval(Duration::from_secs(60)); // 1min
+        let mut tx_interval = interval(Duration::from_secs(3)); // 3s
+        let mut collect_interval = interval(Duration::from_secs(3600)); // 1h
+        let mut results = self.results_receiver.take().expect("Pool running once");
         loop {
             let work = select! {
                 w = async {
@@ -108,71 +269,49 @@ impl Pool {
                     gas_interval.tick().await;
                     Some(InnerFuture::ZeroGas)
                 } => w,
+                w = async {
+                    collect_interval.tick().await;
+                    Some(InnerFuture::Collect)
+                } => w,
+                w = async {
+                    tx_interval.tick().await;
+                    Some(InnerFuture::Txs)
+                } => w,
+                w = async {
+                    results.recv().await.map(|(id, res)| InnerFuture::Sent(id, res))
+                } => w,
             };
 
             match work {
                 Some(InnerFuture::Message(msg)) => self.handle(msg).await,
-                Some(InnerFuture::ZeroGas) => self.check().await,
-                None => break,
-            }
-        }
-    }
-
-    /// verify controller (or 0 gas wallet) is valid controller to miner
-    async fn verify(&self, account: Address) -> bool {
-        if let Ok(res) = self.controller.check(self.miner, account).await {
-            res
-        } else {
-            false
-        }
-    }
-
-    /// check zero gas is working or not
-    async fn check(&mut self) {
-        if !self.zero_gas.is_empty()
-            && check_zero_gas(&self.zero_gas, self.wallet.address())
-                .await
-                .map_err(|e| info!("[Pool] 0 gas check error: {}", e))
-                .is_ok()
-        {
-            let aa = self.zero_gas_wallet.address();
-            if aa == Address::zero() {
-                // create zero gas wallet
-                match create_zero_gas(&self.zero_gas, self.wallet.address()).await {
-                    Ok(new_aa) => {
-                        info!("[Pool] 0 gas wallet fetched: {}", new_aa);
-                        self.zero_gas_wallet = self.zero_gas_wallet.at(new_aa).into();
-                        self.reset_nonce().await;
-
-                        // check aa is valid controller
-                        if self.verify(new_aa).await {
-                            info!("[Pool] 0 gas wallet actived");
-                            self.zero_gas_working = true;
-                        } else {
-                            warn!("[Pool] 0 gas wallet not set to controller");
-                        }
-                    }
-                    Err(e) => {
-                        info!("[Pool] 0 gas create error: {}", e);
+                Some(InnerFuture::ZeroGas) => {
+                    if self.zero_gas.need_check(Utc::now().timestamp()) {
+                        self.check().await;
                     }
                 }
-            } else {
-                if !self.zero_gas_working {
-                    if self.verify(aa).await {
-                        self.zero_gas_working = true;
-                    }
+                Some(InnerFuture::Collect) => self.collect().await,
+                Some(InnerFuture::Txs) => {
+                    self.refresh_fee().await;
+                    self.poll().await;
+                    self.process().await;
+                    self.check_flush();
                 }
+                Some(InnerFuture::Sent(id, res)) => self.on_sent(id, res).await,
+                None => break,
             }
-        } else {
-            self.zero_gas_working = false;
         }
     }
 
-    /// reset zero gas nonce, sync with chain
-    async fn reset_nonce(&mut self) {
-        if let Ok(nonce) = self.zero_gas_wallet.nonce().await {
-            self.zero_gas_nonce = nonce.as_u64();
-        }
+    /// check zero gas is working or not
+    async fn check(&mut self) {
+        let controller = self.controller.clone();
+        let miner = self.miner;
+        self.zero_gas
+            .check(self.wallet.address(), |account| async move {
+                // verify 0 gas wallet is valid controller to miner
+                controller.check(miner, account).await.unwrap_or(false)
+            })
+            .await;
     }
 
     async fn handle(&mut self, msg: PoolMessage) {
@@ -183,98 +322,971 @@ impl Pool {
                 // change controller account
                 let task_address = self.task.address();
                 let stake_address = self.stake.address();
+                let reward_address = self.reward.address();
 
                 if let Ok(signer) = new_signer(self.provider.clone(), wallet.clone()).await {
                     self.task = Task::new(task_address, signer.clone());
-                    self.stake = Stake::new(stake_address, signer);
+                    self.stake = Stake::new(stake_address, signer.clone());
+                    self.reward = Reward::new(reward_address, signer);
                     self.wallet = wallet;
+                    self.nonces = Nonces::default();
                     self.check().await;
                 }
             }
             PoolMessage::AcceptTask(tid, url) => {
-                let func = self.task.accept(U256::from(tid), self.miner, url);
-                self.send(func, true).await;
+                self.enqueue(TxKind::Accept, tid, url.into_bytes().into());
+                self.process().await;
             }
             PoolMessage::SubmitTask(tid, proof) => {
-                let func = self.task.submit(U256::from(tid), proof.into());
-                self.send(func, true).await;
+                self.enqueue(TxKind::Submit, tid, proof.into());
+                self.process().await;
             }
             PoolMessage::SubmitMinerTest(tid, proof) => {
-                let func = self
-                    .stake
-                    .miner_test_submit(U256::from(tid), false, proof.into());
-                self.send(func, true).await;
-            }
-        }
-    }
-
-    #[async_recursion]
-    async fn send(
-        &mut self,
-        func: FunctionCall<Arc<DefaultSigner>, DefaultSigner, ()>,
-        reset: bool,
-    ) {
-        if self.zero_gas_working {
-            match zero_gas(
-                &self.zero_gas,
-                func.tx.clone(),
-                self.chain,
-                self.zero_gas_wallet.address(),
-                self.zero_gas_nonce,
-                &self.wallet,
-            )
-            .await
-            {
-                Ok(Some(txhash)) => {
-                    info!("[Pool] 0 gas Tx submitted, tx: {}", txhash);
-                    self.zero_gas_nonce += 1;
-                    return;
+                self.enqueue(TxKind::MinerTest, tid, proof.into());
+                self.process().await;
+            }
+            PoolMessage::Claim => {
+                let claiming = self.txs.values().any(|t| t.kind == TxKind::Claim);
+                if !claiming {
+                    self.enqueue(TxKind::Claim, 0, Bytes::default());
+                    self.process().await;
+                }
+            }
+            PoolMessage::Flush(done) => {
+                self.flushing.push(done);
+                self.check_flush();
+            }
+        }
+    }
+
+    /// save a new tx to queue, and link to task
+    fn enqueue(&mut self, kind: TxKind, tid: u64, payload: Bytes) {
+        let now = Utc::now().timestamp();
+        let tx = Tx {
+            id: self.next_tx,
+            kind,
+            tid,
+            payload,
+            status: TxStatus::Queued,
+            hash: None,
+            replaced: vec![],
+            nonce: None,
+            max_fee: None,
+            priority_fee: None,
+            sent_block: 0,
+            attempts: 0,
+            next_try: now,
+            created: now,
+            updated: now,
+            error: None,
+        };
+        self.next_tx += 1;
+
+        if kind == TxKind::Submit {
+            let key = pozk_db::Task::to_key(tid);
+            if let Ok(Some(mut t)) = self.db.get::<pozk_db::Task>(&key) {
+                t.tx = Some(tx.id);
+                let _ = self.db.add(&t);
+            }
+        }
+        if kind == TxKind::MinerTest {
+            let key = pozk_db::MinerTest::to_key(tid);
+            if let Ok(Some(mut t)) = self.db.get::<pozk_db::MinerTest>(&key) {
+                t.tx = Some(tx.id);
+                let _ = self.db.add(&t);
+            }
+        }
+
+        self.save(&tx);
+        self.txs.insert(tx.id, tx);
+    }
+
+    fn save(&self, tx: &Tx) {
+        if let Err(e) = self.db.add(tx) {
+            error!("[Pool] save tx {} error: {}", tx.id, e);
+        }
+    }
+
+    /// send the queued txs which can try now, accepts first
+    async fn process(&mut self) {
+        if self.zero_gas.waiting() {
+            return;
+        }
+
+        let now = Utc::now().timestamp();
+        let mut ids: Vec<(bool, u64)> = self
+            .txs
+            .values()
+            .filter(|t| {
+                t.status == TxStatus::Queued && t.next_try <= now && !self.sending.contains(&t.id)
+            })
+            .map(|t| (t.kind != TxKind::Accept, t.id))
+            .collect();
+        ids.sort();
+
+        for (is_submit, id) in ids {
+            // accepts are never limited, the race for tasks is decided by latency
+            let in_flight = self.sending.len()
+                + self
+                    .txs
+                    .values()
+                    .filter(|t| t.status == TxStatus::Sent)
+                    .count();
+            if is_submit && in_flight >= MAX_IN_FLIGHT {
+                break;
+            }
+            self.dispatch(id, false).await;
+        }
+    }
+
+    /// simulate the tx in background before taking a nonce, result back to `on_sent`
+    async fn dispatch(&mut self, id: u64, paid: bool) {
+        let Some(tx) = self.txs.get_mut(&id) else {
+            return;
+        };
+        tx.attempts += 1;
+        let tx = tx.clone();
+        self.sending.insert(id);
+
+        let paid = paid || !self.zero_gas.working();
+        let mut func = self.call(&tx);
+        if !paid {
+            // the 0 gas wallet is the sender
+            func = func.from(self.zero_gas.wallet());
+        }
+        let results = self.results.clone();
+        tokio::spawn(async move {
+            let res = match simulate(func).await {
+                Some(reason) => SendResult::Skipped(reason),
+                None => SendResult::Simulated(paid),
+            };
+            let _ = results.send((id, res));
+        });
+    }
+
+    /// send the simulated tx in background with a local nonce, result back to `on_sent`
+    async fn send_tx(&mut self, id: u64, paid: bool) {
+        let Some(tx) = self.txs.get(&id) else {
+            self.sending.remove(&id);
+            return;
+        };
+        let func = self.call(tx);
+        let results = self.results.clone();
+
+        // the nonce of last attempt is released
+        if let Some(tx) = self.txs.get_mut(&id) {
+            tx.nonce = None;
+        }
+
+        if !paid {
+            if !self.zero_gas.working() {
+                // stopped when simulating
+                self.sending.remove(&id);
+                return self.finish(id, SendResult::Failed("0 gas not working".to_owned()));
+            }
+            let nonce = self.zero_gas.next_nonce();
+            let client = self.zero_gas.client();
+            let aa = self.zero_gas.wallet();
+
+            let chain = self.chain;
+            let wallet = self.wallet.clone();
+            tokio::spawn(async move {
+                let res = match send_zero_gas(client, func.tx, chain, aa, nonce, &wallet).await {
+                    ZeroGasResult::Sent(txhash) => match txhash.parse::<H256>() {
+                        Ok(hash) => SendResult::Sent(hash),
+                        Err(_) => SendResult::Failed(format!("Invalid 0 gas tx: {}", txhash)),
+                    },
+                    ZeroGasResult::Rejected(nonce) => SendResult::ZeroGasRejected(nonce),
+                    ZeroGasResult::Failed(nonce) => SendResult::ZeroGasFailed(nonce),
+                };
+                let _ = results.send((id, res));
+            });
+            return;
+        }
+
+        let nonce = match self.next_nonce().await {
+            Ok(n) => n,
+            Err(e) => {
+                let _ = results.send((id, SendResult::Failed(e.to_string())));
+                return;
+            }
+        };
+        let fees = self.fees();
+        if let Some(tx) = self.txs.get_mut(&id) {
+            tx.nonce = Some(nonce);
+            tx.max_fee = Some(fees.max_fee);
+            tx.priority_fee = Some(fees.priority_fee);
+        }
+
+        let func = self.with_fees(func, fees).nonce(nonce);
+        tokio::spawn(async move {
+            let _ = results.send((id, send(func).await));
+        });
+    }
+
+    /// the result of sending a tx
+    async fn on_sent(&mut self, id: u64, res: SendResult) {
+        if let Some(fees) = self.replacing.remove(&id) {
+            return self.on_replaced(id, fees, res);
+        }
+
+        if let SendResult::Simulated(paid) = res {
+            return self.send_tx(id, paid).await;
+        }
+
+        self.sending.remove(&id);
+        if !self.txs.contains_key(&id) {
+            return;
+        }
+
+        let resend = match res {
+            // nonce of 0 gas wallet maybe wrong, retry with chain nonce, or the tx reverted
+            SendResult::ZeroGasRejected(used_nonce) => {
+                Some(self.zero_gas.on_rejected(used_nonce).await)
+            }
+            // fallback by policy
+            SendResult::ZeroGasFailed(used_nonce) => Some(self.zero_gas.on_failed(used_nonce)),
+            _ => None,
+        };
+        match resend {
+            Some(Resend::ZeroGas) => {
+                // simulated, send again with the synced nonce
+                self.sending.insert(id);
+                return self.send_tx(id, false).await;
+            }
+            Some(Resend::Paid) => {
+                // the sender changed, simulate again
+                if let Some(tx) = self.txs.get_mut(&id) {
+                    tx.attempts -= 1;
+                }
+                return self.dispatch(id, true).await;
+            }
+            Some(Resend::Later) => {
+                return self.finish(id, SendResult::Failed("0 gas failed".to_owned()));
+            }
+            Some(Resend::Reverted) => {
+                return self.finish(id, SendResult::Reverted("0 gas reverted".to_owned()));
+            }
+            None => {}
+        }
+
+        self.finish(id, res);
+    }
+
+    /// update the tx by send result, and report when final
+    fn finish(&mut self, id: u64, res: SendResult) {
+        let Some(mut tx) = self.txs.remove(&id) else {
+            return;
+        };
+        let now = Utc::now().timestamp();
+        tx.updated = now;
+        match res {
+            SendResult::Sent(hash) => {
+                info!("[Pool] Tx {} sent: {:?}", tx.id, hash);
+                tx.status = TxStatus::Sent;
+                tx.hash = Some(hash);
+                tx.sent_block = self.block;
+                tx.error = None;
+            }
+            SendResult::Skipped(reason) => {
+                warn!("[Pool] Tx {} skipped: {}", tx.id, reason);
+                tx.status = TxStatus::Skipped;
+                tx.error = Some(reason);
+            }
+            SendResult::Reverted(err) => {
+                error!("[Pool] Tx {} failed: {}", tx.id, err);
+                tx.status = TxStatus::Reverted;
+                tx.error = Some(err);
+                self.release_nonce(&mut tx);
+            }
+            SendResult::Failed(err) => {
+                retry(&mut tx, err, now);
+                // maybe the nonce is used, check it with chain
+                self.release_nonce(&mut tx);
+                self.nonces.reset();
+            }
+            SendResult::Simulated(_)
+            | SendResult::ZeroGasRejected(_)
+            | SendResult::ZeroGasFailed(_) => {}
+        }
+
+        self.save(&tx);
+        if tx.status.is_final() {
+            self.report(&tx);
+        } else {
+            self.txs.insert(tx.id, tx);
+        }
+        self.check_flush();
+    }
+
+    /// tell main service the tx failed, e.g. release the slots of lost accept
+    fn report(&self, tx: &Tx) {
+        if tx.status == TxStatus::Mined {
+            return;
+        }
+
+        let reason = tx.error.clone().unwrap_or_default();
+        let msg = match tx.kind {
+            TxKind::Accept => ServiceMessage::AcceptFailed(tx.tid, reason),
+            TxKind::Submit => ServiceMessage::SubmitFailed(tx.tid, false, reason),
+            TxKind::MinerTest => ServiceMessage::SubmitFailed(tx.tid, true, reason),
+            TxKind::Collect => {
+                // collect again next round
+                warn!(
+                    "[Pool] collect rewards of epoch {} failed: {}",
+                    tx.tid, reason
+                );
+                return;
+            }
+            TxKind::Claim => {
+                warn!("[Pool] claim unstaking failed: {}", reason);
+                return;
+            }
+        };
+        let _ = self.sender.send(msg);
+    }
+
+    /// the result of replacing a tx, keep waiting the old one if failed
+    fn on_replaced(&mut self, id: u64, fees: Fees, res: SendResult) {
+        let block = self.block;
+        let Some(tx) = self.txs.get_mut(&id) else {
+            return;
+        };
+        match res {
+            SendResult::Sent(hash) => {
+                info!("[Pool] Tx {} replaced: {:?}, fees: {:?}", tx.id, hash, fees);
+                if let Some(old) = tx.hash.replace(hash) {
+                    tx.replaced.push(old);
+                }
+                tx.max_fee = Some(fees.max_fee);
+                tx.priority_fee = Some(fees.priority_fee);
+            }
+            SendResult::Skipped(err) | SendResult::Reverted(err) | SendResult::Failed(err) => {
+                warn!("[Pool] Tx {} replace failed: {}", tx.id, err);
+            }
+            SendResult::Simulated(_)
+            | SendResult::ZeroGasRejected(_)
+            | SendResult::ZeroGasFailed(_) => {}
+        }
+        // waiting more blocks before next replacing
+        tx.sent_block = block;
+        tx.updated = Utc::now().timestamp();
+
+        let tx = tx.clone();
+        self.save(&tx);
+    }
+
+    /// read latest block and network fee
+    async fn refresh_fee(&mut self) {
+        match self.provider.get_block(BlockNumber::Latest).await {
+            Ok(Some(block)) => {
+                self.block = block.number.map(|n| n.as_u64()).unwrap_or(self.block);
+                if self.eip1559 {
+                    self.network_fee = block.base_fee_per_gas;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => debug!("[Pool] get block error: {}", e),
+        }
+
+        if !self.eip1559 && self.gas_price.is_none() {
+            if let Ok(gas) = self.provider.get_gas_price().await {
+                self.network_fee = Some(gas);
+            }
+        }
+    }
+
+    /// fees for new tx, capped by the max fee
+    fn fees(&self) -> Fees {
+        let fees = if self.eip1559 {
+            let base = self.network_fee.unwrap_or(GAS_PRICE.into());
+            Fees {
+                max_fee: base * 2 + self.priority_fee,
+                priority_fee: self.priority_fee,
+            }
+        } else {
+            let gas_price = if let Some(gs) = self.gas_price {
+                gs
+            } else {
+                let gas = self.network_fee.unwrap_or(GAS_PRICE.into());
+                gas / U256::from(10) + gas // 110%
+            };
+            Fees {
+                max_fee: gas_price,
+                priority_fee: gas_price,
+            }
+        };
+
+        match self.fee_cap {
+            Some(cap) if fees.max_fee > cap => {
+                warn!(
+                    "[Pool] fee cap {} lower than network fee {}, txs may not be mined",
+                    cap, fees.max_fee
+                );
+                Fees {
+                    max_fee: cap,
+                    priority_fee: fees.priority_fee.min(cap),
+                }
+            }
+            _ => fees,
+        }
+    }
+
+    fn with_fees(
+        &self,
+        mut func: FunctionCall<Arc<DefaultSigner>, DefaultSigner, ()>,
+        fees: Fees,
+    ) -> FunctionCall<Arc<DefaultSigner>, DefaultSigner, ()> {
+        if self.eip1559 {
+            if let TypedTransaction::Eip1559(inner) = &mut func.tx {
+                inner.max_fee_per_gas = Some(fees.max_fee);
+                inner.max_priority_fee_per_gas = Some(fees.priority_fee);
+                return func;
+            }
+        }
+        func.gas_price(fees.max_fee)
+    }
+
+    /// send the same tx with the same nonce and higher fees
+    fn replace(&mut self, id: u64) {
+        let Some(tx) = self.txs.get(&id) else {
+            return;
+        };
+        let (Some(nonce), Some(max_fee), Some(priority_fee)) =
+            (tx.nonce, tx.max_fee, tx.priority_fee)
+        else {
+            return;
+        };
+
+        let fees = bump(
+            Fees {
+                max_fee,
+                priority_fee,
+            },
+            self.fees(),
+            self.fee_cap,
+        );
+        let Some(fees) = fees else {
+            warn!(
+                "[Pool] Tx {} reached fee cap {:?}, keep waiting",
+                id, self.fee_cap
+            );
+            return;
+        };
+
+        let func = self.with_fees(self.call(tx), fees).nonce(nonce);
+        let results = self.results.clone();
+        self.replacing.insert(id, fees);
+        tokio::spawn(async move {
+            let res = send(func).await;
+            let _ = results.send((id, res));
+        });
+    }
+
+    /// next nonce of controller, sync with chain and the txs in flight when unknown
+    async fn next_nonce(&mut self) -> Result<U256> {
+        if !self.nonces.synced() {
+            let pending = self
+                .provider
+                .get_transaction_count(self.wallet.address(), Some(BlockNumber::Pending.into()))
+                .await?;
+            let in_flight: BTreeSet<U256> = self
+                .txs
+                .values()
+                .filter(|t| t.status == TxStatus::Sent || self.sending.contains(&t.id))
+                .filter_map(|t| t.nonce)
+                .collect();
+            self.nonces.sync(pending, &in_flight);
+        }
+        self.nonces.take().ok_or(anyhow!("Nonce not synced"))
+    }
+
+    /// the nonce of tx not used, take it again by next tx
+    fn release_nonce(&mut self, tx: &mut Tx) {
+        if let Some(nonce) = tx.nonce.take() {
+            self.nonces.release(nonce);
+        }
+    }
+
+    /// check receipts of sent txs
+    async fn poll(&mut self) {
+        let now = Utc::now().timestamp();
+        let ids: Vec<u64> = self
+            .txs
+            .values()
+            .filter(|t| t.status == TxStatus::Sent)
+            .map(|t| t.id)
+            .collect();
+
+        let mut replace = vec![];
+        for id in ids {
+            let Some(mut tx) = self.txs.remove(&id) else {
+                continue;
+            };
+            let hash = tx.hash.unwrap_or_default();
+
+            // any of the replaced txs maybe mined
+            let mut receipt = self.provider.get_transaction_receipt(hash).await;
+            for old in tx.replaced.iter() {
+                if !matches!(receipt, Ok(None)) {
+                    break;
+                }
+                receipt = self.provider.get_transaction_receipt(*old).await;
+            }
+
+            let changed = match receipt {
+                Ok(Some(receipt)) => {
+                    if receipt.status == Some(U64::one()) {
+                        info!(
+                            "[Pool] Tx {} mined, Gas used: {:?}",
+                            tx.id, receipt.gas_used
+                        );
+                        tx.status = TxStatus::Mined;
+                    } else {
+                        error!("[Pool] Tx {} reverted: {:?}", tx.id, hash);
+                        tx.status = TxStatus::Reverted;
+                        tx.error = Some("Reverted".to_owned());
+                    }
+                    true
                 }
                 Ok(None) => {
-                    info!("[Pool] 0 gas Tx failed, nonce: {}", self.zero_gas_nonce);
-                    if reset {
-                        let old_nonce = self.zero_gas_nonce;
-                        self.reset_nonce().await;
-                        if old_nonce != self.zero_gas_nonce {
-                            return self.send(func, false).await;
-                        }
+                    // not mined in time, replace it with higher fees
+                    if tx.nonce.is_some()
+                        && self.block >= tx.sent_block + self.bump_blocks
+                        && !self.replacing.contains_key(&id)
+                    {
+                        replace.push(id);
+                    }
+
+                    // not mined yet, check it still in mempool
+                    if now - tx.updated > DROP_TIMEOUT
+                        && matches!(self.provider.get_transaction(hash).await, Ok(None))
+                    {
+                        retry(&mut tx, "Dropped from mempool".to_owned(), now);
+                        self.release_nonce(&mut tx);
+                        true
+                    } else {
+                        false
                     }
                 }
                 Err(e) => {
-                    error!("{}", e);
+                    debug!("[Pool] Tx {} receipt error: {}", tx.id, e);
+                    false
                 }
+            };
+
+            if changed {
+                tx.updated = now;
+                self.save(&tx);
+            }
+            if tx.status.is_final() {
+                self.report(&tx);
+            } else {
+                self.txs.insert(tx.id, tx);
             }
         }
 
-        let gas_price = if let Some(gs) = self.gas_price {
-            gs
-        } else {
-            let gas = self
-                .task
-                .client_ref()
-                .get_gas_price()
-                .await
-                .unwrap_or(GAS_PRICE.into());
-            gas / U256::from(10) + gas // 110%
+        for id in replace {
+            if self.txs.get(&id).map(|t| t.status) == Some(TxStatus::Sent) {
+                self.replace(id);
+            }
+        }
+    }
+
+    /// reply the flush requests when no submits are sending or waiting receipt, the others
+    /// and the retries in backoff are already saved and resumed after restart
+    fn check_flush(&mut self) {
+        if self.flushing.is_empty() {
+            return;
+        }
+
+        let now = Utc::now().timestamp();
+        let submitting = self.txs.values().any(|t| {
+            matches!(t.kind, TxKind::Submit | TxKind::MinerTest)
+                && (t.status == TxStatus::Sent || t.next_try <= now || self.sending.contains(&t.id))
+        });
+        if !submitting {
+            for done in self.flushing.drain(..) {
+                let _ = done.send(());
+            }
+        }
+    }
+
+    /// collect the rewards of finished epochs when worth the gas
+    async fn collect(&mut self) {
+        let Some(collect) = self.collect else {
+            return;
         };
-        match func.gas_price(gas_price).send().await {
-            Ok(pending) => {
-                if let Ok(receipt) = pending.await {
-                    info!(
-                        "[Pool] Tx submitted, Gas used: {:?}",
-                        receipt.map(|x| x.cumulative_gas_used)
-                    );
-                } else {
-                    error!("[Pool] Tx submit failed");
-                }
+        let current = match self.epoch.get().call().await {
+            Ok(epoch) => epoch.as_u64(),
+            Err(e) => {
+                warn!("[Pool] get epoch error: {}", e);
+                return;
             }
-            Err(err) => {
-                if let Some(rcode) = err.decode_revert::<String>() {
-                    error!("[Pool] Tx failed: {}", rcode);
-                } else {
-                    error!("[Pool] Tx failed: {}", err);
+        };
+
+        let rewards = match self.db.count::<RewardRow>() {
+            Ok(total) => self
+                .db
+                .list::<RewardRow>(0, total)
+                .map(|(r, _)| r)
+                .unwrap_or_default(),
+            Err(_) => return,
+        };
+        let rates = rates(&rewards);
+
+        for (epoch, labors) in collectable(&rewards, current) {
+            let collecting = self
+                .txs
+                .values()
+                .any(|t| t.kind == TxKind::Collect && t.tid == epoch);
+            if collecting {
+                continue;
+            }
+
+            // 0 gas pays for it
+            let gas_cost = if self.zero_gas.working() {
+                U256::zero()
+            } else {
+                let func = self.reward.miner_batch_collect(epoch.into(), self.miner);
+                match func.estimate_gas().await {
+                    Ok(gas) => gas * self.fees().max_fee,
+                    Err(e) => {
+                        debug!("[Pool] estimate collect of epoch {}: {}", epoch, e);
+                        continue;
+                    }
                 }
+            };
+
+            let value = estimate(&labors, &rates);
+            if !collect.worth(value, gas_cost) {
+                debug!(
+                    "[Pool] skip collect epoch {}: {:?}, gas: {}",
+                    epoch, value, gas_cost
+                );
+                continue;
+            }
+
+            info!("[Pool] collect rewards of epoch {}: {:?}", epoch, value);
+            self.enqueue(TxKind::Collect, epoch, Bytes::default());
+        }
+        self.process().await;
+    }
+
+    fn call(&self, tx: &Tx) -> FunctionCall<Arc<DefaultSigner>, DefaultSigner, ()> {
+        let tid = U256::from(tx.tid);
+        match tx.kind {
+            TxKind::Accept => {
+                let url = String::from_utf8_lossy(&tx.payload).into_owned();
+                self.task.accept(tid, self.miner, url)
+            }
+            TxKind::Submit => self.task.submit(tid, tx.payload.clone()),
+            TxKind::MinerTest => self.stake.miner_test_submit(tid, false, tx.payload.clone()),
+            TxKind::Collect => self.reward.miner_batch_collect(tid, self.miner),
+            TxKind::Claim => self.stake.claim(self.miner),
+        }
+    }
+}
+
+/// eth_call the tx before sending, return the reason if it will revert
+async fn simulate(func: FunctionCall<Arc<DefaultSigner>, DefaultSigner, ()>) -> Option<String> {
+    match func.call().await {
+        Ok(()) => None,
+        Err(err) => {
+            if let Some(code) = err.decode_revert::<String>() {
+                Some(revert_reason(&code))
+            } else if err.as_revert().is_some() {
+                Some(err.to_string())
+            } else {
+                // maybe network error, try to send it
+                debug!("[Pool] simulate error: {}", err);
+                None
+            }
+        }
+    }
+}
+
+/// send the tx, not waiting for receipt
+async fn send(func: FunctionCall<Arc<DefaultSigner>, DefaultSigner, ()>) -> SendResult {
+    match func.send().await {
+        Ok(pending) => SendResult::Sent(pending.tx_hash()),
+        Err(err) => {
+            if let Some(rcode) = err.decode_revert::<String>() {
+                SendResult::Reverted(revert_reason(&rcode))
+            } else if err.as_revert().is_some() {
+                SendResult::Reverted(err.to_string())
+            } else {
+                SendResult::Failed(err.to_string())
             }
         }
     }
 }
+
+/// bumped fees to replace a tx, at least `FEE_BUMP` percent higher and not lower than
+/// the current fees, None if over the cap
+fn bump(old: Fees, current: Fees, cap: Option<U256>) -> Option<Fees> {
+    let up = |fee: U256| fee + fee * FEE_BUMP / 100 + 1;
+    let max_fee = up(old.max_fee).max(current.max_fee);
+    let priority_fee = up(old.priority_fee).max(current.priority_fee);
+    if let Some(cap) = cap {
+        if max_fee > cap {
+            return None;
+        }
+    }
+
+    Some(Fees {
+        max_fee,
+        priority_fee: priority_fee.min(max_fee),
+    })
+}
+
+/// queue the failed tx again with backoff, or drop it
+fn retry(tx: &mut Tx, err: String, now: i64) {
+    if tx.attempts >= MAX_ATTEMPTS {
+        error!("[Pool] Tx {} dropped: {}", tx.id, err);
+        tx.status = TxStatus::Dropped;
+    } else {
+        let delay = backoff(tx.attempts);
+        warn!("[Pool] Tx {} retry after {}s: {}", tx.id, delay, err);
+        tx.status = TxStatus::Queued;
+        tx.next_try = now + delay;
+    }
+    tx.error = Some(err);
+}
+
+/// retry delay after the attempts
+fn backoff(attempts: u32) -> i64 {
+    let n = attempts.saturating_sub(1).min(16);
+    (RETRY_BASE << n).min(RETRY_MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::core::rand::{thread_rng, Rng};
+    use pozk_utils::{MockChain, MockZeroGas};
+
+    use crate::ZeroGasPolicy;
+
+    const CHAIN: u64 = 31337;
+
+    fn db() -> Arc<ReDB> {
+        let path = std::env::temp_dir().join(format!("pozk-pool-{}", thread_rng().gen::<u64>()));
+        Arc::new(ReDB::new(&path, true).unwrap())
+    }
+
+    fn config(chain: &MockChain) -> MonitorConfig {
+        let address = || Some(format!("{:?}", Address::random()));
+        MonitorConfig {
+            network: "localhost".to_owned(),
+            endpoints: chain.uri().to_owned(),
+            miner: format!("{:?}", Address::random()),
+            task_address: address(),
+            stake_address: address(),
+            controller_address: address(),
+            reward_address: address(),
+            epoch_address: address(),
+            ..Default::default()
+        }
+    }
+
+    async fn setup(
+        cfg: &MonitorConfig,
+        wallet: &LocalWallet,
+        db: Arc<ReDB>,
+    ) -> (Pool, UnboundedReceiver<(u64, SendResult)>) {
+        let (sender, _) = unbounded_channel();
+        let mut pool = Pool::new(cfg, wallet.clone(), false, db, sender)
+            .await
+            .unwrap();
+        let results = pool.results_receiver.take().unwrap();
+        (pool, results)
+    }
+
+    /// handle the results of background sending until all done
+    async fn settle(pool: &mut Pool, results: &mut UnboundedReceiver<(u64, SendResult)>) {
+        while !pool.sending.is_empty() {
+            let (id, res) = results.recv().await.unwrap();
+            pool.on_sent(id, res).await;
+        }
+    }
+
+    fn status(db: &ReDB, id: u64) -> TxStatus {
+        db.get::<Tx>(&Tx::to_key(id)).unwrap().unwrap().status
+    }
+
+    #[tokio::test]
+    async fn test_pool_skip_keeps_nonce() {
+        let chain = MockChain::start(CHAIN, Some(U256::exp10(9))).await.unwrap();
+        let wallet = LocalWallet::new(&mut thread_rng());
+        let db = db();
+        let (mut pool, mut results) = setup(&config(&chain), &wallet, db.clone()).await;
+
+        // simulation failed, no nonce taken
+        chain.set_revert(Some("T04"));
+        pool.handle(PoolMessage::AcceptTask(1, String::new())).await;
+        settle(&mut pool, &mut results).await;
+        assert_eq!(status(&db, 0), TxStatus::Skipped);
+        assert!(pool.txs.is_empty());
+
+        chain.set_revert(None);
+        pool.handle(PoolMessage::AcceptTask(2, String::new())).await;
+        pool.handle(PoolMessage::AcceptTask(3, String::new())).await;
+        settle(&mut pool, &mut results).await;
+        assert_eq!(chain.pending(wallet.address()), vec![0, 1]);
+
+        chain.mine();
+        pool.poll().await;
+        assert!(pool.txs.is_empty());
+        assert_eq!(status(&db, 1), TxStatus::Mined);
+        assert_eq!(status(&db, 2), TxStatus::Mined);
+    }
+
+    #[tokio::test]
+    async fn test_pool_zero_gas() {
+        let chain = MockChain::start(CHAIN, Some(U256::exp10(9))).await.unwrap();
+        let wallet = LocalWallet::new(&mut thread_rng());
+        let db = db();
+        let (mut pool, mut results) = setup(&config(&chain), &wallet, db.clone()).await;
+
+        let mock = Arc::new(MockZeroGas::start(CHAIN).await.unwrap());
+        mock.allow(wallet.address());
+        pool.zero_gas = ZeroGas::new(
+            Some(mock.clone()),
+            ZeroGasPolicy::Prefer,
+            U256::zero(),
+            db.clone(),
+        );
+        pool.check().await;
+        assert!(pool.zero_gas.working());
+
+        // sent by 0 gas, no controller nonce
+        pool.handle(PoolMessage::AcceptTask(1, String::new())).await;
+        settle(&mut pool, &mut results).await;
+        assert_eq!(mock.sent(), 1);
+        assert_eq!(pool.txs[&0].status, TxStatus::Sent);
+        assert_eq!(pool.txs[&0].nonce, None);
+        assert!(chain.pending(wallet.address()).is_empty());
+
+        // the tx reverted, no fallback
+        mock.set_reject(true);
+        pool.handle(PoolMessage::AcceptTask(2, String::new())).await;
+        settle(&mut pool, &mut results).await;
+        assert!(pool.zero_gas.working());
+        assert_eq!(status(&db, 1), TxStatus::Reverted);
+        assert!(chain.pending(wallet.address()).is_empty());
+
+        // service failure, fallback to pay gas
+        mock.set_down(true);
+        pool.handle(PoolMessage::AcceptTask(3, String::new())).await;
+        settle(&mut pool, &mut results).await;
+        assert!(!pool.zero_gas.working());
+        assert_eq!(mock.sent(), 1);
+        assert_eq!(pool.txs[&2].status, TxStatus::Sent);
+        assert_eq!(pool.txs[&2].nonce, Some(U256::zero()));
+        assert_eq!(chain.pending(wallet.address()), vec![0]);
+
+        // the policy only never pays gas
+        pool.zero_gas = ZeroGas::new(Some(mock.clone()), ZeroGasPolicy::Only, U256::zero(), db);
+        pool.check().await;
+        assert!(pool.zero_gas.waiting());
+        pool.handle(PoolMessage::AcceptTask(4, String::new())).await;
+        settle(&mut pool, &mut results).await;
+        assert_eq!(pool.txs[&3].status, TxStatus::Queued);
+        assert_eq!(chain.pending(wallet.address()), vec![0]);
+    }
+
+    #[tokio::test]
+    async fn test_pool_flush() {
+        let chain = MockChain::start(CHAIN, Some(U256::exp10(9))).await.unwrap();
+        let wallet = LocalWallet::new(&mut thread_rng());
+        let (mut pool, mut results) = setup(&config(&chain), &wallet, db()).await;
+
+        // claim is not waited
+        pool.handle(PoolMessage::Claim).await;
+        settle(&mut pool, &mut results).await;
+        let (done, mut flushed) = oneshot::channel();
+        pool.handle(PoolMessage::Flush(done)).await;
+        assert!(flushed.try_recv().is_ok());
+
+        // waiting the submit mined
+        pool.handle(PoolMessage::SubmitTask(1, vec![1u8])).await;
+        settle(&mut pool, &mut results).await;
+        let (done, mut flushed) = oneshot::channel();
+        pool.handle(PoolMessage::Flush(done)).await;
+        assert!(flushed.try_recv().is_err());
+
+        chain.mine();
+        pool.poll().await;
+        pool.check_flush();
+        assert!(flushed.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_pool_reload() {
+        let chain = MockChain::start(CHAIN, Some(U256::exp10(9))).await.unwrap();
+        let wallet = LocalWallet::new(&mut thread_rng());
+        let cfg = config(&chain);
+        let db = db();
+
+        let (mut pool, mut results) = setup(&cfg, &wallet, db.clone()).await;
+        pool.handle(PoolMessage::AcceptTask(1, String::new())).await;
+        pool.handle(PoolMessage::AcceptTask(2, String::new())).await;
+        settle(&mut pool, &mut results).await;
+        assert_eq!(chain.pending(wallet.address()), vec![0, 1]);
+        drop(pool);
+
+        // restart, the sent txs are waiting receipts, new tx takes the next nonce
+        let (mut pool, mut results) = setup(&cfg, &wallet, db.clone()).await;
+        assert_eq!(pool.txs.len(), 2);
+        assert!(pool.txs.values().all(|t| t.status == TxStatus::Sent));
+        pool.handle(PoolMessage::AcceptTask(3, String::new())).await;
+        settle(&mut pool, &mut results).await;
+        assert_eq!(chain.pending(wallet.address()), vec![0, 1, 2]);
+        assert_eq!(pool.txs[&2].nonce, Some(U256::from(2)));
+
+        chain.mine();
+        pool.poll().await;
+        assert!(pool.txs.is_empty());
+        for id in 0..3 {
+            assert_eq!(status(&db, id), TxStatus::Mined);
+        }
+    }
+
+    #[test]
+    fn test_bump() {
+        let gwei = |n: u64| U256::from(n) * U256::exp10(9);
+        let old = Fees {
+            max_fee: gwei(10),
+            priority_fee: gwei(1),
+        };
+        let current = Fees {
+            max_fee: gwei(5),
+            priority_fee: gwei(1),
+        };
+
+        let fees = bump(old, current, None).unwrap();
+        assert!(fees.max_fee > gwei(12));
+        assert!(fees.priority_fee > gwei(1));
+
+        // network fee higher than bumped
+        let current = Fees {
+            max_fee: gwei(30),
+            priority_fee: gwei(2),
+        };
+        let fees = bump(old, current, Some(gwei(50))).unwrap();
+        assert_eq!(fees.max_fee, gwei(30));
+
+        // over the cap
+        assert!(bump(old, current, Some(gwei(11))).is_none());
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), 5);
+        assert_eq!(backoff(2), 10);
+        assert_eq!(backoff(3), 20);
+        assert_eq!(backoff(10), RETRY_MAX);
+        assert_eq!(backoff(100), RETRY_MAX);
+    }
+}
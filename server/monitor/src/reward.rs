@@ -0,0 +1,106 @@
+use ethers::prelude::*;
+use pozk_db::Reward;
+use std::collections::{BTreeMap, HashMap};
+
+/// auto collect rewards when the estimated value is over threshold
+#[derive(Clone, Copy, Debug)]
+pub struct Collect {
+    /// min estimated reward
+    pub min_reward: U256,
+    /// reward token per gas token
+    pub gas_ratio: U256,
+}
+
+impl Collect {
+    /// the reward must cover min reward and the gas cost
+    pub fn worth(&self, value: Option<U256>, gas_cost: U256) -> bool {
+        match value {
+            Some(value) => value >= self.min_reward + gas_cost * self.gas_ratio,
+            // never collected this prover, no rate to estimate
+            None => true,
+        }
+    }
+}
+
+/// the uncollected labors of finished epochs, epoch => labors
+pub fn collectable(rewards: &[Reward], current: u64) -> BTreeMap<u64, Vec<&Reward>> {
+    let mut epochs: BTreeMap<u64, Vec<&Reward>> = BTreeMap::new();
+    for r in rewards {
+        if r.epoch < current && r.amount.is_none() && !r.work.is_zero() {
+            epochs.entry(r.epoch).or_default().push(r);
+        }
+    }
+    epochs
+}
+
+/// reward per work of the latest collected epoch, prover => (amount, work)
+pub fn rates(rewards: &[Reward]) -> HashMap<Address, (U256, U256)> {
+    let mut rates = HashMap::new();
+    // rewards sorted by epoch, later will replace
+    for r in rewards {
+        if let Some(amount) = r.amount {
+            if !r.work.is_zero() {
+                rates.insert(r.prover, (amount, r.work));
+            }
+        }
+    }
+    rates
+}
+
+/// estimate the reward of labors, None if some prover never collected
+pub fn estimate(labors: &[&Reward], rates: &HashMap<Address, (U256, U256)>) -> Option<U256> {
+    let mut value = U256::zero();
+    for r in labors {
+        let (amount, work) = rates.get(&r.prover)?;
+        value += r.work * amount / work;
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reward(epoch: u64, prover: Address, work: u64, amount: Option<u64>) -> Reward {
+        Reward {
+            epoch,
+            prover,
+            work: work.into(),
+            labors: 1,
+            amount: amount.map(U256::from),
+            updated: 0,
+        }
+    }
+
+    #[test]
+    fn test_collect() {
+        let p1 = Address::random();
+        let p2 = Address::random();
+        let rewards = vec![
+            reward(1, p1, 10, Some(100)),
+            reward(2, p1, 10, Some(200)),
+            reward(3, p1, 5, None),
+            reward(3, p2, 5, None),
+            reward(4, p1, 5, None),
+            reward(5, p1, 5, None),
+        ];
+
+        let epochs = collectable(&rewards, 5);
+        assert_eq!(epochs.keys().copied().collect::<Vec<_>>(), vec![3, 4]);
+
+        let rates = rates(&rewards);
+        assert_eq!(rates.get(&p1), Some(&(200.into(), 10.into())));
+        assert_eq!(estimate(&epochs[&4], &rates), Some(100.into()));
+        // p2 never collected
+        assert_eq!(estimate(&epochs[&3], &rates), None);
+
+        let collect = Collect {
+            min_reward: 50.into(),
+            gas_ratio: 10.into(),
+        };
+        assert!(collect.worth(Some(100.into()), 5.into()));
+        assert!(!collect.worth(Some(100.into()), 6.into()));
+        assert!(!collect.worth(Some(40.into()), 0.into()));
+        assert!(collect.worth(None, 100.into()));
+    }
+}
@@ -1,18 +1,28 @@
 use anyhow::{anyhow, Result};
 use ethers::prelude::*;
 use pozk_db::{ReDB, ScanBlock};
-use pozk_utils::{new_providers, DefaultProvider, ProverType, ServiceMessage};
+use pozk_utils::{new_providers, DefaultProvider, Epoch, ProverType, ServiceMessage};
 use std::{
     collections::HashMap,
     sync::Arc,
     time::{Duration, Instant},
 };
-use tokio::{sync::mpsc::UnboundedSender, time::timeout};
+use tokio::{
+    sync::{mpsc::UnboundedSender, watch},
+    task::JoinHandle,
+    time::timeout,
+};
 
 use crate::MonitorConfig;
 
 const TIMEOUT: u64 = 10;
 
+/// read the epoch and maintenance status every 30s
+const EPOCH_INTERVAL: u64 = 30;
+
+/// how many blocks to pull each time when syncing the provers
+const PROVER_SYNC_STEP: u64 = 10000;
+
 /// The CreateTask event on the listener chain is sent to the channel when the specified event is listened.
 /// The event is processed by TxService.
 /// Different events are processed by different channels.
@@ -24,6 +34,9 @@ pub struct Scan {
     providers: Vec<Arc<DefaultProvider>>,
     init_start: Option<u64>,
     filter: Filter,
+    prover_address: Address,
+    prover_start: Option<u64>,
+    epoch_address: Address,
     events: HashMap<H256, EventType>,
     sender: UnboundedSender<ServiceMessage>,
     db: Arc<ReDB>,
@@ -34,8 +47,17 @@ enum EventType {
     CreateTask,
     AcceptTask,
     ApproveProver,
+    RegisterProver,
+    UpgradeProver,
     StopProver,
     MinerTest,
+    MinerTestCancel,
+    MinerTestRequire,
+    MinerTestSubmit,
+    MinerLabor,
+    MinerCollect,
+    AddUnstaking,
+    NewEpoch,
 }
 
 #[derive(Clone, Debug, EthEvent)]
@@ -70,6 +92,44 @@ struct ApproveProver {
     approved: bool,
 }
 
+#[derive(Clone, Debug, EthEvent)]
+struct RegisterProver {
+    prover: Address,
+    ptype: u8,
+    work: U256,
+    version: U256,
+    overtime: U256,
+    verifier: Address,
+    name: String,
+    types: String,
+}
+
+#[derive(Clone, Debug, EthEvent)]
+struct UpgradeProver {
+    prover: Address,
+    ptype: u8,
+    work: U256,
+    version: U256,
+    overtime: U256,
+    verifier: Address,
+    name: String,
+    types: String,
+}
+
+impl ApproveProver {
+    fn message(self) -> ServiceMessage {
+        ServiceMessage::ApproveProver(
+            self.prover,
+            self.version.as_u64(),
+            self.overtime.as_u64(),
+            ProverType::from_byte(self.ptype),
+            self.types,
+            self.minable,
+            self.approved,
+        )
+    }
+}
+
 #[derive(Clone, Debug, EthEvent)]
 struct StopProver {
     prover: Address,
@@ -85,6 +145,54 @@ struct MinerTestCreate {
     publics: Bytes,
 }
 
+#[derive(Clone, Debug, EthEvent)]
+struct MinerTestCancel {
+    id: U256,
+}
+
+#[derive(Clone, Debug, EthEvent)]
+struct MinerTestRequire {
+    id: U256,
+    account: Address,
+    prover: Address,
+    amount: U256,
+}
+
+#[derive(Clone, Debug, EthEvent)]
+struct MinerTestSubmit {
+    id: U256,
+    submit_at: U256,
+}
+
+#[derive(Clone, Debug, EthEvent)]
+struct MinerLabor {
+    epoch: U256,
+    prover: Address,
+    miner: Address,
+    work: U256,
+}
+
+#[derive(Clone, Debug, EthEvent)]
+struct MinerCollect {
+    epoch: U256,
+    prover: Address,
+    miner: Address,
+    amount: U256,
+}
+
+#[derive(Clone, Debug, EthEvent)]
+struct AddUnstaking {
+    epoch: U256,
+    account: Address,
+    amount: U256,
+}
+
+#[derive(Clone, Debug, EthEvent)]
+struct NewEpoch {
+    height: U256,
+    start_time: U256,
+}
+
 impl Scan {
     pub async fn new(
         cfg: MonitorConfig,
@@ -95,29 +203,64 @@ impl Scan {
         let miner = cfg.miner()?;
 
         let (task_address, init_start) = cfg.task_address()?;
-        let (prover_address, _) = cfg.prover_address()?;
+        let (prover_address, prover_start) = cfg.prover_address()?;
         let (stake_address, _) = cfg.stake_address()?;
-        let addresses = vec![task_address, prover_address, stake_address];
+        let (reward_address, _) = cfg.reward_address()?;
+        let (epoch_address, _) = cfg.epoch_address()?;
+        let addresses = vec![
+            task_address,
+            prover_address,
+            stake_address,
+            reward_address,
+            epoch_address,
+        ];
 
         let create_task = CreateTask::signature();
         let accept_task = AcceptTask::signature();
         let approve_prover = ApproveProver::signature();
+        let register_prover = RegisterProver::signature();
+        let upgrade_prover = UpgradeProver::signature();
         let stop_prover = StopProver::signature();
         let miner_test = MinerTestCreate::signature();
+        let miner_test_cancel = MinerTestCancel::signature();
+        let miner_test_require = MinerTestRequire::signature();
+        let miner_test_submit = MinerTestSubmit::signature();
+        let miner_labor = MinerLabor::signature();
+        let miner_collect = MinerCollect::signature();
+        let add_unstaking = AddUnstaking::signature();
+        let new_epoch = NewEpoch::signature();
 
         let mut events = HashMap::new();
         events.insert(create_task, EventType::CreateTask);
         events.insert(accept_task, EventType::AcceptTask);
         events.insert(approve_prover, EventType::ApproveProver);
+        events.insert(register_prover, EventType::RegisterProver);
+        events.insert(upgrade_prover, EventType::UpgradeProver);
         events.insert(stop_prover, EventType::StopProver);
         events.insert(miner_test, EventType::MinerTest);
+        events.insert(miner_test_cancel, EventType::MinerTestCancel);
+        events.insert(miner_test_require, EventType::MinerTestRequire);
+        events.insert(miner_test_submit, EventType::MinerTestSubmit);
+        events.insert(miner_labor, EventType::MinerLabor);
+        events.insert(miner_collect, EventType::MinerCollect);
+        events.insert(add_unstaking, EventType::AddUnstaking);
+        events.insert(new_epoch, EventType::NewEpoch);
 
         let topics = vec![
             create_task,
             accept_task,
             approve_prover,
+            register_prover,
+            upgrade_prover,
             stop_prover,
             miner_test,
+            miner_test_cancel,
+            miner_test_require,
+            miner_test_submit,
+            miner_labor,
+            miner_collect,
+            add_unstaking,
+            new_epoch,
         ];
 
         // filter
@@ -129,13 +272,17 @@ impl Scan {
             providers,
             init_start,
             filter,
+            prover_address,
+            prover_start,
+            epoch_address,
             events,
             sender,
             db,
         })
     }
 
-    pub fn run(self) {
+    /// start scanning until stop, the cursor is always saved when stopped
+    pub fn run(self, stop: watch::Receiver<bool>) -> JoinHandle<()> {
         tokio::spawn(async move {
             let mut next_index = 0;
             let mut start_block = self.init_start.clone();
@@ -152,7 +299,20 @@ impl Scan {
                 start_block = None; // first run, use latest block
             }
 
-            loop {
+            // the approved provers for auto install
+            if self.cfg.sync_provers {
+                let sync = self.clone();
+                tokio::spawn(async move {
+                    for i in 0..sync.providers.len() {
+                        match sync.sync_provers(i).await {
+                            Ok(()) => break,
+                            Err(e) => error!("[Scan] sync provers: {e:?}"),
+                        }
+                    }
+                });
+            }
+
+            while !*stop.borrow() {
                 let start = if start_block.is_some() {
                     start_block
                 } else {
@@ -164,7 +324,10 @@ impl Scan {
                 };
 
                 if let Some(start) = start {
-                    start_block = Some(self.running(start, next_index).await);
+                    start_block = Some(self.running(start, next_index, &stop).await);
+                    if *stop.borrow() {
+                        break;
+                    }
                 }
 
                 // waiting 2s
@@ -175,12 +338,30 @@ impl Scan {
                 }
                 error!("[Scan] provider failure, next_index: {}", next_index);
             }
-        });
+            info!("[Scan] stopped");
+        })
     }
 
     /// Loop running scan task
-    pub async fn running(&self, mut start: u64, i: usize) -> u64 {
+    pub async fn running(&self, mut start: u64, i: usize, stop: &watch::Receiver<bool>) -> u64 {
+        let mut epoch_polled: Option<Instant> = None;
         loop {
+            if *stop.borrow() {
+                return start;
+            }
+
+            let polling = epoch_polled
+                .map(|t| t.elapsed().as_secs() >= EPOCH_INTERVAL)
+                .unwrap_or(true);
+            if polling {
+                match self.epoch(i).await {
+                    Ok(msg) => {
+                        self.sender.send(msg).expect("Missing scan receiver");
+                        epoch_polled = Some(Instant::now());
+                    }
+                    Err(e) => error!("[Scan] read epoch: {e:?}"),
+                }
+            }
             let start_time = Instant::now();
 
             let end_res = if let Ok(res) = timeout(
@@ -225,6 +406,10 @@ impl Scan {
             };
 
             for log in logs {
+                if matches!(self.events.get(&log.topics[0]), Some(EventType::NewEpoch)) {
+                    // read the new epoch soon
+                    epoch_polled = None;
+                }
                 match self.parse_log(log) {
                     Ok(Some(op)) => {
                         self.sender.send(op).expect("Missing scan receiver"); // panic if channel is missing
@@ -253,6 +438,80 @@ impl Scan {
         }
     }
 
+    /// replay the prover contract to the latest block,
+    /// send the latest name and approval of all provers
+    async fn sync_provers(&self, i: usize) -> Result<()> {
+        let provider = &self.providers[i];
+        let latest = provider.get_block_number().await?.as_u64();
+        let filter = Filter::new().address(self.prover_address).topic0(vec![
+            ApproveProver::signature(),
+            RegisterProver::signature(),
+            UpgradeProver::signature(),
+        ]);
+
+        let mut names = HashMap::new();
+        let mut approvals = HashMap::new();
+        let mut from = self.prover_start.unwrap_or(0);
+        while from <= latest {
+            let to = (from + PROVER_SYNC_STEP - 1).min(latest);
+            let logs = provider
+                .get_logs(&filter.clone().from_block(from).to_block(to))
+                .await?;
+            for log in logs {
+                match self.events.get(&log.topics[0]) {
+                    Some(EventType::ApproveProver) => {
+                        let ap = <ApproveProver as EthEvent>::decode_log(&log.into())?;
+                        approvals.insert(ap.prover, ap);
+                    }
+                    Some(EventType::RegisterProver) => {
+                        let rp = <RegisterProver as EthEvent>::decode_log(&log.into())?;
+                        names.insert(rp.prover, rp.name);
+                    }
+                    Some(EventType::UpgradeProver) => {
+                        let up = <UpgradeProver as EthEvent>::decode_log(&log.into())?;
+                        names.insert(up.prover, up.name);
+                    }
+                    _ => {}
+                }
+            }
+            from = to + 1;
+        }
+
+        info!(
+            "[Scan] synced provers: {} registered, {} approved",
+            names.len(),
+            approvals.len()
+        );
+        for (prover, name) in names {
+            self.sender
+                .send(ServiceMessage::RegisterProver(prover, name))
+                .expect("Missing scan receiver");
+        }
+        for (_, ap) in approvals {
+            self.sender
+                .send(ap.message())
+                .expect("Missing scan receiver");
+        }
+
+        Ok(())
+    }
+
+    /// read the epoch status from contract
+    async fn epoch(&self, i: usize) -> Result<ServiceMessage> {
+        let epoch = Epoch::new(self.epoch_address, self.providers[i].clone());
+        let height = epoch.height().call().await?.as_u64();
+        let start_time = epoch.start_time().call().await?.as_u64();
+        let period = epoch.period().call().await?.as_u64();
+        let maintenance = epoch.maintenance().call().await?;
+
+        Ok(ServiceMessage::Epoch(
+            height,
+            start_time,
+            period,
+            maintenance,
+        ))
+    }
+
     fn parse_log(&self, log: Log) -> Result<Option<ServiceMessage>> {
         let topic = &log.topics[0];
         if let Some(et) = self.events.get(topic) {
@@ -264,6 +523,7 @@ impl Scan {
                     Ok(Some(ServiceMessage::CreateTask(
                         tid,
                         ct.prover,
+                        ct.fee,
                         ct.inputs.to_vec(),
                         ct.publics.to_vec(),
                     )))
@@ -281,16 +541,27 @@ impl Scan {
                 }
                 EventType::ApproveProver => {
                     let ap = <ApproveProver as EthEvent>::decode_log(&log.into())?;
-                    let version = ap.version.as_u64();
-                    let overtime = ap.overtime.as_u64();
-                    let ptype = ProverType::from_byte(ap.ptype);
                     info!(
                         "[Scan] fetch new ApproveProver: {} - {}",
-                        ap.prover, version
+                        ap.prover, ap.version
                     );
-                    Ok(Some(ServiceMessage::ApproveProver(
-                        ap.prover, version, overtime, ptype, ap.types,
-                    )))
+                    Ok(Some(ap.message()))
+                }
+                EventType::RegisterProver => {
+                    let rp = <RegisterProver as EthEvent>::decode_log(&log.into())?;
+                    info!(
+                        "[Scan] fetch new RegisterProver: {} - {}",
+                        rp.prover, rp.name
+                    );
+                    Ok(Some(ServiceMessage::RegisterProver(rp.prover, rp.name)))
+                }
+                EventType::UpgradeProver => {
+                    let up = <UpgradeProver as EthEvent>::decode_log(&log.into())?;
+                    info!(
+                        "[Scan] fetch new UpgradeProver: {} - {}",
+                        up.prover, up.name
+                    );
+                    Ok(Some(ServiceMessage::RegisterProver(up.prover, up.name)))
                 }
                 EventType::StopProver => {
                     let ap = <StopProver as EthEvent>::decode_log(&log.into())?;
@@ -315,6 +586,68 @@ impl Scan {
                         Ok(None)
                     }
                 }
+                EventType::MinerTestCancel => {
+                    let mc = <MinerTestCancel as EthEvent>::decode_log(&log.into())?;
+                    // no account in event, service checks the test is mine
+                    let id = mc.id.as_u64();
+                    debug!("[Scan] fetch new MinerTestCancel: {}", id);
+                    Ok(Some(ServiceMessage::MinerTestCancel(id)))
+                }
+                EventType::MinerTestRequire => {
+                    let mr = <MinerTestRequire as EthEvent>::decode_log(&log.into())?;
+                    if mr.account == self.miner {
+                        let id = mr.id.as_u64();
+                        info!("[Scan] fetch new MinerTestRequire: {} - {}", id, mr.prover);
+                        Ok(Some(ServiceMessage::MinerTestRequire(
+                            id, mr.prover, mr.amount,
+                        )))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                EventType::MinerTestSubmit => {
+                    let ms = <MinerTestSubmit as EthEvent>::decode_log(&log.into())?;
+                    let id = ms.id.as_u64();
+                    debug!("[Scan] fetch new MinerTestSubmit: {}", id);
+                    Ok(Some(ServiceMessage::MinerTestSubmit(id)))
+                }
+                EventType::MinerLabor => {
+                    let ml = <MinerLabor as EthEvent>::decode_log(&log.into())?;
+                    if ml.miner == self.miner {
+                        let epoch = ml.epoch.as_u64();
+                        debug!("[Scan] fetch new MinerLabor: {} - {}", epoch, ml.prover);
+                        Ok(Some(ServiceMessage::MinerLabor(epoch, ml.prover, ml.work)))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                EventType::MinerCollect => {
+                    let mc = <MinerCollect as EthEvent>::decode_log(&log.into())?;
+                    if mc.miner == self.miner {
+                        let epoch = mc.epoch.as_u64();
+                        info!("[Scan] fetch new MinerCollect: {} - {}", epoch, mc.prover);
+                        Ok(Some(ServiceMessage::MinerCollect(
+                            epoch, mc.prover, mc.amount,
+                        )))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                EventType::AddUnstaking => {
+                    let au = <AddUnstaking as EthEvent>::decode_log(&log.into())?;
+                    if au.account == self.miner {
+                        let epoch = au.epoch.as_u64();
+                        info!("[Scan] fetch new AddUnstaking: {} - {}", epoch, au.amount);
+                        Ok(Some(ServiceMessage::AddUnstaking(epoch, au.amount)))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                EventType::NewEpoch => {
+                    let ne = <NewEpoch as EthEvent>::decode_log(&log.into())?;
+                    info!("[Scan] fetch new NewEpoch: {}", ne.height);
+                    Ok(None)
+                }
             }
         } else {
             Err(anyhow!("missing topic"))
@@ -0,0 +1,499 @@
+use chrono::Utc;
+use ethers::{prelude::*, types::transaction::eip2718::TypedTransaction};
+use pozk_db::{ReDB, ZeroGasWallet};
+use pozk_utils::ZeroGasClient;
+use std::{future::Future, sync::Arc};
+
+use crate::ZeroGasPolicy;
+
+/// re-check the working 0 gas service every 10min
+const CHECK_INTERVAL: i64 = 600;
+
+/// result of sending a tx by 0 gas
+pub enum ZeroGasResult {
+    /// tx hash
+    Sent(String),
+    /// rejected by chain with the nonce, maybe wrong nonce or reverted
+    Rejected(u64),
+    /// the 0 gas service failed with the nonce
+    Failed(u64),
+}
+
+/// how to resend the tx which rejected by 0 gas
+#[derive(Debug, PartialEq, Eq)]
+pub enum Resend {
+    /// nonce changed, resend by 0 gas
+    ZeroGas,
+    /// fallback to pay gas
+    Paid,
+    /// waiting 0 gas service, retry later
+    Later,
+    /// the tx reverted, it fails with any payer
+    Reverted,
+}
+
+/// The 0 gas state of pool: the AA wallet of controller, its local nonce and allowance
+pub struct ZeroGas {
+    client: Option<Arc<dyn ZeroGasClient>>,
+    policy: ZeroGasPolicy,
+    /// alert when allowance lower than it
+    low_balance: U256,
+    db: Arc<ReDB>,
+    status: ZeroGasWallet,
+}
+
+impl ZeroGas {
+    pub fn new(
+        client: Option<Arc<dyn ZeroGasClient>>,
+        policy: ZeroGasPolicy,
+        low_balance: U256,
+        db: Arc<ReDB>,
+    ) -> Self {
+        let client = if policy == ZeroGasPolicy::Paid {
+            None
+        } else {
+            client
+        };
+
+        Self {
+            client,
+            policy,
+            low_balance,
+            db,
+            status: ZeroGasWallet::default(),
+        }
+    }
+
+    pub fn working(&self) -> bool {
+        self.status.working
+    }
+
+    pub fn wallet(&self) -> Address {
+        self.status.wallet
+    }
+
+    /// txs must wait until 0 gas working
+    pub fn waiting(&self) -> bool {
+        self.policy == ZeroGasPolicy::Only && !self.status.working
+    }
+
+    /// check again soon when not working
+    pub fn need_check(&self, now: i64) -> bool {
+        self.client.is_some()
+            && (!self.status.working || now - self.status.updated >= CHECK_INTERVAL)
+    }
+
+    /// check zero gas is working or not, the AA wallet must be verified as controller
+    pub async fn check<F, Fut>(&mut self, controller: Address, verify: F)
+    where
+        F: FnOnce(Address) -> Fut,
+        Fut: Future<Output = bool>,
+    {
+        let Some(client) = self.client.clone() else {
+            self.status.working = false;
+            return;
+        };
+
+        if self.status.controller != controller || self.status.updated == 0 {
+            self.load(controller).await;
+        }
+        self.status.updated = Utc::now().timestamp();
+
+        match client.balance(controller).await {
+            Ok(balance) if !balance.is_zero() => {
+                self.status.balance = balance;
+                self.status.low = balance < self.low_balance;
+                if self.status.low {
+                    warn!(
+                        "[Pool] 0 gas balance low: {} < {}",
+                        balance, self.low_balance
+                    );
+                }
+            }
+            Ok(_) => {
+                info!("[Pool] 0 gas check error: No permission");
+                self.status.balance = U256::zero();
+                self.status.low = true;
+                self.status.working = false;
+                return self.save();
+            }
+            Err(e) => {
+                info!("[Pool] 0 gas check error: {}", e);
+                self.status.working = false;
+                return self.save();
+            }
+        }
+
+        if self.status.wallet == Address::zero() {
+            // create zero gas wallet
+            match client.create(controller).await {
+                Ok(wallet) => {
+                    info!("[Pool] 0 gas wallet fetched: {}", wallet);
+                    self.status.wallet = wallet;
+                    self.reset_nonce().await;
+
+                    // check aa is valid controller
+                    if verify(wallet).await {
+                        info!("[Pool] 0 gas wallet actived");
+                        self.status.working = true;
+                    } else {
+                        warn!("[Pool] 0 gas wallet not set to controller");
+                    }
+                }
+                Err(e) => {
+                    info!("[Pool] 0 gas create error: {}", e);
+                }
+            }
+        } else if !self.status.working && verify(self.status.wallet).await {
+            info!("[Pool] 0 gas wallet actived");
+            self.status.working = true;
+        }
+        self.save();
+    }
+
+    /// load the AA wallet and nonce of controller from db
+    async fn load(&mut self, controller: Address) {
+        let key = ZeroGasWallet::to_key(&controller);
+        match self.db.get::<ZeroGasWallet>(key).ok().flatten() {
+            Some(status) => {
+                self.status = status;
+                self.status.working = false;
+
+                // the local nonce maybe ahead of chain when txs not mined
+                let local = self.status.nonce;
+                self.reset_nonce().await;
+                self.status.nonce = self.status.nonce.max(local);
+                info!(
+                    "[Pool] 0 gas wallet loaded: {}, nonce: {}",
+                    self.status.wallet, self.status.nonce
+                );
+            }
+            None => {
+                self.status = ZeroGasWallet {
+                    controller,
+                    ..Default::default()
+                };
+            }
+        }
+    }
+
+    fn save(&self) {
+        if let Err(e) = self.db.add(&self.status) {
+            error!("[Pool] save 0 gas wallet: {}", e);
+        }
+    }
+
+    /// take a nonce for new tx
+    pub fn next_nonce(&mut self) -> u64 {
+        let nonce = self.status.nonce;
+        self.status.nonce += 1;
+        self.save();
+        nonce
+    }
+
+    /// reset zero gas nonce, sync with chain
+    pub async fn reset_nonce(&mut self) {
+        if let Some(client) = &self.client {
+            if let Ok(nonce) = client.nonce(self.status.wallet).await {
+                self.status.nonce = nonce;
+                self.save();
+            }
+        }
+    }
+
+    /// the tx rejected by chain with the nonce, sync nonce with chain, resend by 0 gas if
+    /// nonce changed. fallback only when the allowance is used up or the service is failure,
+    /// otherwise the tx reverted
+    pub async fn on_rejected(&mut self, used: u64) -> Resend {
+        self.reset_nonce().await;
+        if self.status.nonce != used {
+            return Resend::ZeroGas;
+        }
+
+        let Some(client) = self.client.clone() else {
+            return self.fallback();
+        };
+        match client.balance(self.status.controller).await {
+            Ok(balance) if !balance.is_zero() => {
+                info!("[Pool] 0 gas Tx reverted, nonce: {}", used);
+                Resend::Reverted
+            }
+            Ok(_) => {
+                warn!("[Pool] 0 gas allowance used up");
+                self.status.balance = U256::zero();
+                self.status.low = true;
+                self.fallback()
+            }
+            Err(e) => {
+                warn!("[Pool] 0 gas check error: {}", e);
+                self.fallback()
+            }
+        }
+    }
+
+    /// the 0 gas service failed with the nonce, fallback by policy
+    pub fn on_failed(&mut self, used: u64) -> Resend {
+        // the nonce not used
+        if self.status.nonce == used + 1 {
+            self.status.nonce = used;
+        }
+        self.fallback()
+    }
+
+    /// stop using 0 gas until check it working again soon
+    fn fallback(&mut self) -> Resend {
+        self.status.working = false;
+        self.save();
+        match self.policy {
+            ZeroGasPolicy::Only => {
+                warn!("[Pool] 0 gas failed, waiting the service");
+                Resend::Later
+            }
+            _ => {
+                warn!("[Pool] 0 gas failed, fallback to pay gas");
+                Resend::Paid
+            }
+        }
+    }
+
+    /// the client for sending in background, only call when working
+    pub fn client(&self) -> Arc<dyn ZeroGasClient> {
+        self.client.clone().expect("0 gas working without client")
+    }
+}
+
+/// send tx by the 0 gas client
+pub async fn send_zero_gas(
+    client: Arc<dyn ZeroGasClient>,
+    tx: TypedTransaction,
+    chain: u64,
+    wallet: Address,
+    nonce: u64,
+    owner: &LocalWallet,
+) -> ZeroGasResult {
+    match client.send(tx, chain, wallet, nonce, owner).await {
+        Ok(Some(txhash)) => {
+            info!("[Pool] 0 gas Tx submitted, tx: {}", txhash);
+            ZeroGasResult::Sent(txhash)
+        }
+        Ok(None) => {
+            info!("[Pool] 0 gas Tx rejected, nonce: {}", nonce);
+            ZeroGasResult::Rejected(nonce)
+        }
+        Err(e) => {
+            error!("[Pool] 0 gas Tx failed: {}", e);
+            ZeroGasResult::Failed(nonce)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::core::rand::{thread_rng, Rng};
+    use pozk_utils::MockZeroGas;
+
+    const CHAIN: u64 = 31337;
+
+    fn tx() -> TypedTransaction {
+        TransactionRequest::new()
+            .to(Address::random())
+            .data(vec![1u8, 2, 3])
+            .into()
+    }
+
+    fn db() -> Arc<ReDB> {
+        let path =
+            std::env::temp_dir().join(format!("pozk-zero-gas-{}", thread_rng().gen::<u64>()));
+        Arc::new(ReDB::new(&path, true).unwrap())
+    }
+
+    async fn setup(policy: ZeroGasPolicy) -> (Arc<MockZeroGas>, ZeroGas, LocalWallet, Arc<ReDB>) {
+        let mock = Arc::new(MockZeroGas::start(CHAIN).await.unwrap());
+        let owner = LocalWallet::new(&mut thread_rng());
+        mock.allow(owner.address());
+
+        let db = db();
+        let mut zg = ZeroGas::new(Some(mock.clone()), policy, U256::exp10(16), db.clone());
+        zg.check(owner.address(), |_| async { true }).await;
+        (mock, zg, owner, db)
+    }
+
+    async fn send(zg: &mut ZeroGas, owner: &LocalWallet) -> ZeroGasResult {
+        let (client, wallet) = (zg.client(), zg.wallet());
+        let nonce = zg.next_nonce();
+        send_zero_gas(client, tx(), CHAIN, wallet, nonce, owner).await
+    }
+
+    #[tokio::test]
+    async fn test_zero_gas_send() {
+        let (mock, mut zg, owner, _) = setup(ZeroGasPolicy::Prefer).await;
+        assert!(zg.working());
+        assert_ne!(zg.wallet(), Address::zero());
+
+        assert!(matches!(
+            send(&mut zg, &owner).await,
+            ZeroGasResult::Sent(_)
+        ));
+        assert!(matches!(
+            send(&mut zg, &owner).await,
+            ZeroGasResult::Sent(_)
+        ));
+        assert_eq!(mock.sent(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_zero_gas_permission() {
+        let mock = Arc::new(MockZeroGas::start(CHAIN).await.unwrap());
+        let mut zg = ZeroGas::new(
+            Some(mock.clone()),
+            ZeroGasPolicy::Prefer,
+            U256::zero(),
+            db(),
+        );
+        zg.check(Address::random(), |_| async { true }).await;
+        assert!(!zg.working());
+
+        // wallet not set to controller
+        let owner = Address::random();
+        mock.allow(owner);
+        zg.check(owner, |_| async { false }).await;
+        assert!(!zg.working());
+        zg.check(owner, |_| async { true }).await;
+        assert!(zg.working());
+
+        // allowance used up
+        mock.set_balance(owner, U256::zero());
+        zg.check(owner, |_| async { true }).await;
+        assert!(!zg.working());
+
+        // no 0 gas service
+        let mut zg = ZeroGas::new(None, ZeroGasPolicy::Prefer, U256::zero(), db());
+        zg.check(owner, |_| async { true }).await;
+        assert!(!zg.working());
+        assert!(!zg.need_check(0));
+
+        // policy never use 0 gas
+        let mut zg = ZeroGas::new(Some(mock), ZeroGasPolicy::Paid, U256::zero(), db());
+        zg.check(owner, |_| async { true }).await;
+        assert!(!zg.working());
+    }
+
+    #[tokio::test]
+    async fn test_zero_gas_nonce_reset() {
+        let (mock, mut zg, owner, _) = setup(ZeroGasPolicy::Prefer).await;
+
+        // nonce changed by other txs of the wallet
+        mock.set_nonce(zg.wallet(), 3);
+        let ZeroGasResult::Rejected(used) = send(&mut zg, &owner).await else {
+            panic!("must be rejected with wrong nonce");
+        };
+        assert_eq!(zg.on_rejected(used).await, Resend::ZeroGas);
+
+        assert!(matches!(
+            send(&mut zg, &owner).await,
+            ZeroGasResult::Sent(_)
+        ));
+        assert_eq!(mock.sent(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_zero_gas_fallback() {
+        let (mock, mut zg, owner, _) = setup(ZeroGasPolicy::Prefer).await;
+
+        // the nonce is right, but the tx reverted, paying gas not helps
+        mock.set_reject(true);
+        let ZeroGasResult::Rejected(used) = send(&mut zg, &owner).await else {
+            panic!("must be rejected");
+        };
+        assert_eq!(zg.on_rejected(used).await, Resend::Reverted);
+        assert!(zg.working());
+
+        // allowance used up, need pay gas
+        mock.set_balance(owner.address(), U256::zero());
+        let ZeroGasResult::Rejected(used) = send(&mut zg, &owner).await else {
+            panic!("must be rejected");
+        };
+        assert_eq!(zg.on_rejected(used).await, Resend::Paid);
+        assert!(!zg.working());
+        assert!(!zg.waiting());
+        assert!(zg.need_check(Utc::now().timestamp()));
+        assert_eq!(mock.sent(), 0);
+
+        // service failure, the nonce is released
+        let (mock, mut zg, owner, _) = setup(ZeroGasPolicy::Prefer).await;
+        mock.set_down(true);
+        let ZeroGasResult::Failed(used) = send(&mut zg, &owner).await else {
+            panic!("must be failed");
+        };
+        assert_eq!(zg.on_failed(used), Resend::Paid);
+        assert!(!zg.working());
+        assert_eq!(zg.next_nonce(), used);
+
+        // only policy waits the service
+        let (mock, mut zg, owner, _) = setup(ZeroGasPolicy::Only).await;
+        mock.set_down(true);
+        let ZeroGasResult::Failed(used) = send(&mut zg, &owner).await else {
+            panic!("must be failed");
+        };
+        assert_eq!(zg.on_failed(used), Resend::Later);
+        assert!(zg.waiting());
+
+        // service recovered
+        mock.set_down(false);
+        zg.check(owner.address(), |_| async { true }).await;
+        assert!(!zg.waiting());
+        assert!(matches!(
+            send(&mut zg, &owner).await,
+            ZeroGasResult::Sent(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_zero_gas_invalid_signature() {
+        let (mock, mut zg, _, _) = setup(ZeroGasPolicy::Prefer).await;
+        let other = LocalWallet::new(&mut thread_rng());
+        assert!(matches!(
+            send(&mut zg, &other).await,
+            ZeroGasResult::Rejected(0)
+        ));
+        assert_eq!(mock.sent(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_zero_gas_low_balance() {
+        let (mock, mut zg, owner, db) = setup(ZeroGasPolicy::Prefer).await;
+        let key = ZeroGasWallet::to_key(&owner.address()).to_vec();
+        let status = db.get::<ZeroGasWallet>(&key).unwrap().unwrap();
+        assert!(!status.low);
+
+        mock.set_balance(owner.address(), U256::exp10(15));
+        zg.check(owner.address(), |_| async { true }).await;
+        assert!(zg.working());
+        let status = db.get::<ZeroGasWallet>(&key).unwrap().unwrap();
+        assert!(status.low);
+        assert_eq!(status.balance, U256::exp10(15));
+    }
+
+    #[tokio::test]
+    async fn test_zero_gas_persisted_nonce() {
+        let (mock, mut zg, owner, db) = setup(ZeroGasPolicy::Prefer).await;
+        let wallet = zg.wallet();
+
+        // sent to service, but not mined yet
+        assert_eq!(zg.next_nonce(), 0);
+        assert_eq!(zg.next_nonce(), 1);
+
+        // restart, keep the local nonce which ahead of chain
+        let mut zg = ZeroGas::new(Some(mock.clone()), ZeroGasPolicy::Prefer, U256::zero(), db);
+        zg.check(owner.address(), |_| async { true }).await;
+        assert!(zg.working());
+        assert_eq!(zg.wallet(), wallet);
+        assert_eq!(zg.next_nonce(), 2);
+
+        // chain is ahead
+        mock.set_nonce(wallet, 5);
+        zg.reset_nonce().await;
+        assert_eq!(zg.next_nonce(), 5);
+    }
+}
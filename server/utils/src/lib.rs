@@ -6,8 +6,21 @@ mod networks;
 #[cfg(feature = "contracts")]
 pub use networks::*;
 
+#[cfg(feature = "contracts")]
+mod zero_gas;
+#[cfg(feature = "contracts")]
+pub use zero_gas::*;
+
+#[cfg(feature = "mock")]
+mod mock;
+#[cfg(feature = "mock")]
+pub use mock::*;
+
 mod message;
 pub use message::*;
 
 mod p2p;
 pub use p2p::*;
+
+mod revert;
+pub use revert::*;
@@ -1,29 +1,74 @@
-use ethers::prelude::{Address, LocalWallet};
-use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use ethers::prelude::{Address, LocalWallet, U256};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
 
 use crate::networks::ProverType;
 
 pub enum ServiceMessage {
-    /// tid, prover, inputs, publics
-    CreateTask(u64, Address, Vec<u8>, Vec<u8>),
+    /// tid, prover, fee, inputs, publics
+    CreateTask(u64, Address, U256, Vec<u8>, Vec<u8>),
     /// tid, overtime, is_me
     AcceptTask(u64, i64, bool),
-    /// prover, version, overtime, prover type, supported types
-    ApproveProver(Address, u64, u64, ProverType, String),
+    /// prover, version, overtime, prover type, supported types, minable, approved
+    ApproveProver(Address, u64, u64, ProverType, String, bool, bool),
+    /// prover, name: registered or upgraded on chain
+    RegisterProver(Address, String),
     /// tid, proof
     UploadProof(String, Vec<u8>),
     /// controller wallet and sk bytes
     ChangeController(LocalWallet, Vec<u8>),
-    /// pull prover, tag, name and overtime, prover type, supported types
-    PullProver(Address, String, String, u64, ProverType, String),
+    /// pull and install prover, the job id of pulling
+    PullProver(PullRequest, String),
     /// remove prover
     RemoveProver(Address),
     /// test id, prover, overtime, inputs, publics
     MinerTest(u64, Address, i64, Vec<u8>, Vec<u8>),
+    /// test id: cancelled on chain
+    MinerTestCancel(u64),
+    /// test id, prover, staking amount: test required for the miner
+    MinerTestRequire(u64, Address, U256),
+    /// test id: proof accepted on chain
+    MinerTestSubmit(u64),
+    /// prover, fixture inputs and publics, None to use the sample of past tasks
+    Benchmark(Address, Option<(Vec<u8>, Vec<u8>)>),
     /// task from player service
     ApiTask(String, i64),
     /// Heartbeat for cleanup task
     TaskHeartbeat,
+    /// tid, reason: accept tx skipped or failed
+    AcceptFailed(u64, String),
+    /// tid or test id, is miner test, reason: submit tx skipped or failed
+    SubmitFailed(u64, bool, String),
+    /// stop accepting new tasks, reply when current work and txs done
+    Drain(oneshot::Sender<()>),
+    /// epoch, prover, work of miner
+    MinerLabor(u64, Address, U256),
+    /// epoch, prover, collected amount of miner
+    MinerCollect(u64, Address, U256),
+    /// epoch, amount of miner unstaking
+    AddUnstaking(u64, U256),
+    /// claim the unstaked tokens by controller
+    Claim,
+    /// epoch height, start time, period, maintenance
+    Epoch(u64, u64, u64, bool),
+}
+
+/// The prover image to pull and install
+pub struct PullRequest {
+    pub prover: Address,
+    pub tag: String,
+    pub name: String,
+    pub overtime: u64,
+    pub ptype: ProverType,
+    pub types: String,
+    /// the expected image digest, pinned at first pull if None
+    pub digest: Option<String>,
+    /// the registry of image, None is the default
+    pub registry: Option<String>,
+    /// the organisation of image, None is the default
+    pub org: Option<String>,
 }
 
 pub fn new_service_channel() -> (
@@ -0,0 +1,430 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, State},
+    routing::{get, post},
+    Json, Router,
+};
+use ethers::{prelude::*, types::transaction::eip2718::TypedTransaction};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use crate::networks::{create_zero_gas, generate_eip712_data, zero_gas, zero_gas_balance};
+use crate::zero_gas::ZeroGasClient;
+
+#[derive(Default)]
+struct MockState {
+    /// controller => 0 gas allowance
+    balances: HashMap<Address, U256>,
+    /// owner => AA wallet
+    wallets: HashMap<Address, Address>,
+    /// AA wallet => nonce in chain
+    nonces: HashMap<Address, u64>,
+    /// chain rejects all txs
+    reject: bool,
+    /// the service is failure, all requests get invalid responses
+    down: bool,
+    /// accepted txs: (wallet, to, data)
+    txs: Vec<(Address, Address, String)>,
+}
+
+struct MockContext {
+    chain: u64,
+    state: Mutex<MockState>,
+}
+
+/// In-process stand-in of the 0 gas service, validates the EIP-712 signature of every
+/// function call with the nonce it keeps for the AA wallet.
+pub struct MockZeroGas {
+    uri: String,
+    ctx: Arc<MockContext>,
+}
+
+impl MockZeroGas {
+    pub async fn start(chain: u64) -> Result<Self> {
+        let ctx = Arc::new(MockContext {
+            chain,
+            state: Mutex::new(MockState::default()),
+        });
+
+        let app = Router::new()
+            .route("/balanceof/:controller", get(balance_of))
+            .route("/create", post(create))
+            .route("/functioncall", post(function_call))
+            .with_state(ctx.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let uri = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        Ok(Self { uri, ctx })
+    }
+
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// give the controller permission to use 0 gas
+    pub fn allow(&self, controller: Address) {
+        self.set_balance(controller, U256::exp10(18));
+    }
+
+    /// change the 0 gas allowance of controller
+    pub fn set_balance(&self, controller: Address, balance: U256) {
+        let mut state = self.ctx.state.lock().unwrap();
+        state.balances.insert(controller, balance);
+    }
+
+    /// change the nonce of AA wallet in chain
+    pub fn set_nonce(&self, wallet: Address, nonce: u64) {
+        self.ctx.state.lock().unwrap().nonces.insert(wallet, nonce);
+    }
+
+    /// reject all txs, like the chain is failure
+    pub fn set_reject(&self, reject: bool) {
+        self.ctx.state.lock().unwrap().reject = reject;
+    }
+
+    /// make the service failure, like it is down
+    pub fn set_down(&self, down: bool) {
+        self.ctx.state.lock().unwrap().down = down;
+    }
+
+    /// the count of accepted txs
+    pub fn sent(&self) -> usize {
+        self.ctx.state.lock().unwrap().txs.len()
+    }
+}
+
+#[async_trait]
+impl ZeroGasClient for MockZeroGas {
+    async fn balance(&self, controller: Address) -> Result<U256> {
+        zero_gas_balance(&self.uri, controller).await
+    }
+
+    async fn create(&self, controller: Address) -> Result<Address> {
+        create_zero_gas(&self.uri, controller).await
+    }
+
+    async fn nonce(&self, wallet: Address) -> Result<u64> {
+        let state = self.ctx.state.lock().unwrap();
+        Ok(state.nonces.get(&wallet).copied().unwrap_or(0))
+    }
+
+    async fn send(
+        &self,
+        tx: TypedTransaction,
+        chain: u64,
+        wallet: Address,
+        nonce: u64,
+        owner: &LocalWallet,
+    ) -> Result<Option<String>> {
+        zero_gas(&self.uri, tx, chain, wallet, nonce, owner).await
+    }
+}
+
+async fn balance_of(
+    State(ctx): State<Arc<MockContext>>,
+    Path(controller): Path<Address>,
+) -> Json<Value> {
+    let state = ctx.state.lock().unwrap();
+    if state.down {
+        return Json(json!({}));
+    }
+    let amount = state.balances.get(&controller).copied().unwrap_or_default();
+    Json(json!({ "amount": format!("{:#x}", amount) }))
+}
+
+#[derive(Deserialize)]
+struct CreateForm {
+    owner: Address,
+}
+
+async fn create(State(ctx): State<Arc<MockContext>>, Json(form): Json<CreateForm>) -> Json<Value> {
+    let mut state = ctx.state.lock().unwrap();
+    let wallet = *state
+        .wallets
+        .entry(form.owner)
+        .or_insert_with(Address::random);
+    Json(json!({ "wallet": format!("{:?}", wallet) }))
+}
+
+#[derive(Deserialize)]
+struct CallForm {
+    wallet: String,
+    to: String,
+    data: String,
+    value: String,
+    v: u64,
+    r: String,
+    s: String,
+    owner: Address,
+}
+
+async fn function_call(
+    State(ctx): State<Arc<MockContext>>,
+    Json(form): Json<CallForm>,
+) -> Json<Value> {
+    let mut state = ctx.state.lock().unwrap();
+    if state.down {
+        return Json(json!({}));
+    }
+    if state.reject {
+        return Json(json!({ "error": "execution reverted" }));
+    }
+
+    let Ok(wallet) = form.wallet.parse::<Address>() else {
+        return Json(json!({ "error": "invalid wallet" }));
+    };
+    if state.wallets.get(&form.owner) != Some(&wallet) {
+        return Json(json!({ "error": "invalid owner" }));
+    }
+
+    // the signature must be signed with the nonce in chain
+    let nonce = state.nonces.get(&wallet).copied().unwrap_or(0);
+    let tdata = generate_eip712_data(
+        ctx.chain,
+        nonce,
+        &form.wallet,
+        &form.to,
+        &form.value,
+        &form.data,
+    );
+    let (Ok(r), Ok(s)) = (
+        U256::from_str_radix(form.r.trim_start_matches("0x"), 16),
+        U256::from_str_radix(form.s.trim_start_matches("0x"), 16),
+    ) else {
+        return Json(json!({ "error": "invalid signature" }));
+    };
+    let signature = Signature { r, s, v: form.v };
+    match signature.recover_typed_data(&tdata) {
+        Ok(signer) if signer == form.owner => {}
+        _ => return Json(json!({ "error": "invalid signature" })),
+    }
+
+    let to = form.to.parse::<Address>().unwrap_or_default();
+    state.txs.push((wallet, to, form.data));
+    state.nonces.insert(wallet, nonce + 1);
+    Json(json!({ "tx_hash": format!("{:?}", H256::random()) }))
+}
+
+#[derive(Default)]
+struct ChainState {
+    block: u64,
+    /// None is legacy chain without base fee
+    base_fee: Option<U256>,
+    /// account => nonce of mined txs
+    nonces: HashMap<Address, u64>,
+    /// (account, nonce) => pending tx hash, replaced by the same nonce
+    pending: BTreeMap<(Address, u64), H256>,
+    /// tx hash => (account, nonce), all received txs
+    txs: HashMap<H256, (Address, u64)>,
+    /// tx hash => mined block
+    mined: HashMap<H256, u64>,
+    /// eth_call and gas estimation revert with the reason
+    revert: Option<String>,
+}
+
+impl ChainState {
+    /// the nonce of next tx, includes the pending txs without gap
+    fn pending_nonce(&self, account: Address) -> u64 {
+        let mut nonce = self.nonces.get(&account).copied().unwrap_or(0);
+        while self.pending.contains_key(&(account, nonce)) {
+            nonce += 1;
+        }
+        nonce
+    }
+}
+
+struct ChainContext {
+    chain: u64,
+    state: Mutex<ChainState>,
+}
+
+/// In-process stand-in of the chain JSON-RPC, txs stay pending until mined,
+/// the txs after a nonce gap are never mined.
+pub struct MockChain {
+    uri: String,
+    ctx: Arc<ChainContext>,
+}
+
+impl MockChain {
+    /// start the chain, with base fee for EIP-1559 or legacy gas price
+    pub async fn start(chain: u64, base_fee: Option<U256>) -> Result<Self> {
+        let ctx = Arc::new(ChainContext {
+            chain,
+            state: Mutex::new(ChainState {
+                block: 1,
+                base_fee,
+                ..Default::default()
+            }),
+        });
+
+        let app = Router::new().route("/", post(rpc)).with_state(ctx.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let uri = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        Ok(Self { uri, ctx })
+    }
+
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// revert all calls with the reason, None to recover
+    pub fn set_revert(&self, reason: Option<&str>) {
+        self.ctx.state.lock().unwrap().revert = reason.map(|r| r.to_owned());
+    }
+
+    /// the nonces of pending txs of account
+    pub fn pending(&self, account: Address) -> Vec<u64> {
+        let state = self.ctx.state.lock().unwrap();
+        state
+            .pending
+            .keys()
+            .filter(|(a, _)| *a == account)
+            .map(|(_, n)| *n)
+            .collect()
+    }
+
+    /// mine a block with the pending txs which have no nonce gap
+    pub fn mine(&self) {
+        let mut state = self.ctx.state.lock().unwrap();
+        state.block += 1;
+        let block = state.block;
+
+        let accounts: HashSet<Address> = state.pending.keys().map(|(a, _)| *a).collect();
+        for account in accounts {
+            let mut nonce = state.nonces.get(&account).copied().unwrap_or(0);
+            while let Some(hash) = state.pending.remove(&(account, nonce)) {
+                state.mined.insert(hash, block);
+                nonce += 1;
+            }
+            state.nonces.insert(account, nonce);
+        }
+    }
+}
+
+fn revert_error(reason: &str) -> Value {
+    let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+    data.extend(ethers::abi::encode(&[ethers::abi::Token::String(
+        reason.to_owned(),
+    )]));
+    json!({
+        "code": 3,
+        "message": format!("execution reverted: {}", reason),
+        "data": format!("0x{}", hex::encode(data)),
+    })
+}
+
+async fn rpc(State(ctx): State<Arc<ChainContext>>, Json(req): Json<Value>) -> Json<Value> {
+    let id = req["id"].clone();
+    let params = req["params"].clone();
+    let mut state = ctx.state.lock().unwrap();
+
+    let res: std::result::Result<Value, Value> = match req["method"].as_str().unwrap_or("") {
+        "eth_chainId" => Ok(json!(format!("{:#x}", ctx.chain))),
+        "eth_blockNumber" => Ok(json!(format!("{:#x}", state.block))),
+        "eth_getBlockByNumber" => {
+            let block = Block::<H256> {
+                number: Some(state.block.into()),
+                hash: Some(H256::from_low_u64_be(state.block)),
+                base_fee_per_gas: state.base_fee,
+                ..Default::default()
+            };
+            Ok(serde_json::to_value(block).unwrap_or_default())
+        }
+        "eth_gasPrice" => Ok(json!(format!(
+            "{:#x}",
+            state.base_fee.unwrap_or(U256::exp10(9))
+        ))),
+        "eth_getTransactionCount" => {
+            let account: Address = serde_json::from_value(params[0].clone()).unwrap_or_default();
+            let nonce = if params[1] == "pending" {
+                state.pending_nonce(account)
+            } else {
+                state.nonces.get(&account).copied().unwrap_or(0)
+            };
+            Ok(json!(format!("{:#x}", nonce)))
+        }
+        "eth_call" => match &state.revert {
+            Some(reason) => Err(revert_error(reason)),
+            None => Ok(json!(format!(
+                "0x{}",
+                hex::encode(H256::from_low_u64_be(1))
+            ))),
+        },
+        "eth_estimateGas" => match &state.revert {
+            Some(reason) => Err(revert_error(reason)),
+            None => Ok(json!("0x5208")),
+        },
+        "eth_sendRawTransaction" => {
+            let raw = params[0].as_str().unwrap_or("").trim_start_matches("0x");
+            let raw = hex::decode(raw).unwrap_or_default();
+            let rlp = ethers::utils::rlp::Rlp::new(&raw);
+            match TypedTransaction::decode_signed(&rlp) {
+                Ok((tx, signature)) => {
+                    let from = signature.recover(tx.sighash()).unwrap_or_default();
+                    let nonce = tx.nonce().map(|n| n.as_u64()).unwrap_or(0);
+                    if nonce < state.nonces.get(&from).copied().unwrap_or(0) {
+                        Err(json!({ "code": -32000, "message": "nonce too low" }))
+                    } else {
+                        let hash = H256::from(ethers::utils::keccak256(&raw));
+                        state.pending.insert((from, nonce), hash);
+                        state.txs.insert(hash, (from, nonce));
+                        Ok(json!(hash))
+                    }
+                }
+                Err(e) => Err(json!({ "code": -32000, "message": e.to_string() })),
+            }
+        }
+        "eth_getTransactionReceipt" => {
+            let hash: H256 = serde_json::from_value(params[0].clone()).unwrap_or_default();
+            match (state.mined.get(&hash), state.txs.get(&hash)) {
+                (Some(block), Some((from, _))) => {
+                    let receipt = TransactionReceipt {
+                        transaction_hash: hash,
+                        block_number: Some((*block).into()),
+                        from: *from,
+                        gas_used: Some(21000.into()),
+                        status: Some(1.into()),
+                        ..Default::default()
+                    };
+                    Ok(serde_json::to_value(receipt).unwrap_or_default())
+                }
+                _ => Ok(Value::Null),
+            }
+        }
+        "eth_getTransactionByHash" => {
+            let hash: H256 = serde_json::from_value(params[0].clone()).unwrap_or_default();
+            match state.txs.get(&hash) {
+                Some((from, nonce)) => {
+                    let tx = Transaction {
+                        hash,
+                        from: *from,
+                        nonce: (*nonce).into(),
+                        ..Default::default()
+                    };
+                    Ok(serde_json::to_value(tx).unwrap_or_default())
+                }
+                None => Ok(Value::Null),
+            }
+        }
+        method => Err(json!({ "code": -32601, "message": format!("{} not found", method) })),
+    };
+
+    Json(match res {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    })
+}
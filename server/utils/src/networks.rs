@@ -14,6 +14,7 @@ use std::sync::Arc;
 use tracing::error;
 
 const GAS_PRICE: u64 = 50;
+const PRIORITY_FEE: u64 = 1_000_000; // 0.001 GWEI
 
 // Vesting contract with abi
 abigen!(Token, "public/ABI/Token.json");
@@ -147,6 +148,11 @@ pub fn pozk_gas_price(network: &str) -> Option<U256> {
     }
 }
 
+/// the default priority fee per gas of EIP-1559 tx
+pub fn pozk_priority_fee() -> U256 {
+    U256::from(PRIORITY_FEE)
+}
+
 pub type DefaultProvider = Provider<Http>;
 
 pub fn new_providers(rpcs: &[String]) -> Vec<Arc<DefaultProvider>> {
@@ -170,17 +176,25 @@ pub async fn new_signer(
 }
 
 pub async fn check_zero_gas(uri: &str, controller: Address) -> Result<()> {
+    if zero_gas_balance(uri, controller).await?.is_zero() {
+        Err(anyhow!("No permission"))
+    } else {
+        Ok(())
+    }
+}
+
+/// the remaining 0 gas allowance of controller
+pub async fn zero_gas_balance(uri: &str, controller: Address) -> Result<U256> {
     let res = reqwest::get(format!("{}/balanceof/{:?}", uri, controller))
         .await?
         .json::<Value>()
         .await?;
-    if let Some(amount) = res.pointer("/amount") {
-        if amount != "0x0" {
-            return Ok(());
-        }
-    }
+    let amount = res
+        .pointer("/amount")
+        .and_then(|a| a.as_str())
+        .ok_or(anyhow!("Invalid response"))?;
 
-    Err(anyhow!("No permission"))
+    Ok(U256::from_str_radix(amount.trim_start_matches("0x"), 16)?)
 }
 
 pub async fn create_zero_gas(uri: &str, controller: Address) -> Result<Address> {
@@ -276,7 +290,7 @@ fn eip712_type(name: &str, t: &str) -> Eip712DomainType {
     }
 }
 
-fn generate_eip712_data(
+pub(crate) fn generate_eip712_data(
     chain: u64,
     nonce: u64,
     from: &str,
@@ -332,7 +346,17 @@ pub fn check_task_proxy_list(signer: &Address) -> bool {
     PROXY_LIST_ACCOUNTS.contains(signer)
 }
 
+/// the approximate value of U256 in f64, for scoring and metrics
+pub fn u256_to_f64(value: U256) -> f64 {
+    value
+        .0
+        .iter()
+        .rev()
+        .fold(0.0, |acc, limb| acc * 18446744073709551616.0 + *limb as f64)
+}
+
 #[tokio::test]
+#[ignore = "calls the live 0 gas service"]
 async fn test_zero_gas() {
     let uri = "https://gas.zypher.network";
     let account: Address = "0x5Ef51c9f449DB7Be2f0c636C6C137e65B8B96B9B"
@@ -349,3 +373,11 @@ async fn test_zero_gas() {
         .unwrap();
     assert!(check_zero_gas(uri, account2).await.is_err());
 }
+
+#[test]
+fn test_u256_to_f64() {
+    assert_eq!(u256_to_f64(U256::zero()), 0.0);
+    assert_eq!(u256_to_f64(U256::from(12345u64)), 12345.0);
+    assert_eq!(u256_to_f64(U256::exp10(18)), 1e18);
+    assert_eq!(u256_to_f64(U256::exp10(30)), 1e30);
+}
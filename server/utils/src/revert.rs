@@ -0,0 +1,43 @@
+/// The meaning of revert codes in PoZK contracts
+pub fn revert_message(code: &str) -> Option<&'static str> {
+    let msg = match code {
+        "T01" => "invalid prover",
+        "T02" => "not a controller of miner",
+        "T03" => "not a miner of the prover",
+        "T04" => "task already accepted",
+        "T05" => "task not proving or over time",
+        "T06" => "not in proxy list",
+        "T11" => "invalid url",
+        "T98" => "duplicate proof",
+        "T99" => "invalid proof",
+        "S96" => "unknown miner test",
+        "S97" => "duplicate miner test proof",
+        "S98" => "miner test over time",
+        "S99" => "invalid miner test proof",
+        "E00" => "network in maintenance",
+        "R02" => "epoch not over",
+        "R03" => "rewards already collected",
+        _ => return None,
+    };
+    Some(msg)
+}
+
+/// readable revert reason with the code, e.g. "T04: task already accepted"
+pub fn revert_reason(code: &str) -> String {
+    match revert_message(code) {
+        Some(msg) => format!("{}: {}", code, msg),
+        None => code.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revert_reason() {
+        assert_eq!(revert_reason("T04"), "T04: task already accepted");
+        assert_eq!(revert_reason("S98"), "S98: miner test over time");
+        assert_eq!(revert_reason("X01"), "X01");
+    }
+}
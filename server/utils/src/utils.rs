@@ -1,39 +1,121 @@
 use anyhow::{anyhow, Result};
-use once_cell::sync::OnceCell;
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use tokio::fs;
 
-static BASE_PATH: OnceCell<PathBuf> = OnceCell::new();
-static API_SERVER: OnceCell<String> = OnceCell::new();
+/// the benchmark sample is refreshed at most once a day
+const SAMPLE_MAX_AGE: Duration = Duration::from_secs(86400);
 
-pub fn init_path_and_server(path: &str, server: &str) {
-    BASE_PATH
-        .set(PathBuf::from(path))
-        .expect("Unable set BASE_PATH");
-    API_SERVER
-        .set(server.to_owned())
-        .expect("Unable set API_SERVER");
+/// The files of tasks in base path: inputs, proofs and samples
+#[derive(Clone, Debug)]
+pub struct TaskFiles {
+    base: PathBuf,
 }
 
-pub async fn write_task_input(tid: &str, inputs: Vec<u8>, publics: Vec<u8>) -> Result<()> {
-    let mut path = BASE_PATH.get().expect("Missing BASE PATH").clone();
-    path.push(tid);
+impl TaskFiles {
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self { base: base.into() }
+    }
 
-    let mut bytes = (inputs.len() as u32).to_be_bytes().to_vec();
-    bytes.extend(inputs);
-    bytes.extend(publics);
+    pub async fn write_task_input(
+        &self,
+        tid: &str,
+        inputs: Vec<u8>,
+        publics: Vec<u8>,
+    ) -> Result<()> {
+        let mut bytes = (inputs.len() as u32).to_be_bytes().to_vec();
+        bytes.extend(inputs);
+        bytes.extend(publics);
+
+        fs::write(self.base.join(tid), bytes).await?;
+        Ok(())
+    }
 
-    fs::write(path, bytes).await?;
-    Ok(())
-}
+    pub async fn read_task_input(&self, tid: &str) -> Result<Vec<u8>> {
+        let bytes = fs::read(self.base.join(tid)).await?;
+        Ok(bytes)
+    }
 
-pub async fn read_task_input(tid: &str) -> Result<Vec<u8>> {
-    let mut path = BASE_PATH.get().expect("Missing BASE PATH").clone();
-    path.push(tid);
+    /// keep a task input of prover as the benchmark sample,
+    /// only written when the sample is missing or older than a day
+    pub async fn write_sample_input(
+        &self,
+        prover: &str,
+        inputs: &[u8],
+        publics: &[u8],
+    ) -> Result<()> {
+        let name = format!("sample-{}", prover);
+        if let Ok(meta) = fs::metadata(self.base.join(&name)).await {
+            let age = meta.modified().ok().and_then(|t| t.elapsed().ok());
+            if matches!(age, Some(age) if age < SAMPLE_MAX_AGE) {
+                return Ok(());
+            }
+        }
 
-    let bytes = fs::read(path).await?;
-    Ok(bytes)
+        self.write_task_input(&name, inputs.to_vec(), publics.to_vec())
+            .await
+    }
+
+    pub async fn read_sample_input(&self, prover: &str) -> Result<Vec<u8>> {
+        self.read_task_input(&format!("sample-{}", prover)).await
+    }
+
+    pub async fn remove_task_input(&self, tid: &str) -> Result<()> {
+        fs::remove_file(self.base.join(tid)).await?;
+        Ok(())
+    }
+
+    pub async fn write_task_proof(&self, tid: &str, proof: Vec<u8>) -> Result<()> {
+        fs::write(self.base.join(format!("proof-{}", tid)), proof).await?;
+        Ok(())
+    }
+
+    pub async fn read_task_proof(&self, tid: &str) -> Result<Vec<u8>> {
+        let path = self.base.join(format!("proof-{}", tid));
+        let bytes = fs::read(&path).await?;
+        fs::remove_file(path).await?;
+
+        Ok(bytes)
+    }
+
+    pub async fn remove_task_proof(&self, tid: &str) -> Result<()> {
+        fs::remove_file(self.base.join(format!("proof-{}", tid))).await?;
+        Ok(())
+    }
+
+    /// remove the task files which not modified in retention (seconds),
+    /// the files of kept ids (sid or `sample-{prover}`) and their proofs are skipped,
+    /// except the proofs of API tasks (`proof-p-*`) when `api_proofs` (e.g. over quota).
+    /// return the count and bytes of removed files
+    pub async fn prune_task_files(
+        &self,
+        retention: u64,
+        keep: &HashSet<String>,
+        api_proofs: bool,
+    ) -> Result<(usize, u64)> {
+        prune_files(&self.base, retention, keep, api_proofs).await
+    }
+
+    /// bytes of the files in base path, includes the database
+    pub fn base_path_usage(&self) -> u64 {
+        fn dir_size(path: &Path) -> u64 {
+            let Ok(entries) = std::fs::read_dir(path) else {
+                return 0;
+            };
+            entries
+                .flatten()
+                .map(|entry| match entry.metadata() {
+                    Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+                    Ok(meta) => meta.len(),
+                    Err(_) => 0,
+                })
+                .sum()
+        }
+
+        dir_size(&self.base)
+    }
 }
 
 pub async fn parse_task_input(data: Vec<u8>) -> Result<(Vec<u8>, Vec<u8>)> {
@@ -51,34 +133,48 @@ pub async fn parse_task_input(data: Vec<u8>) -> Result<(Vec<u8>, Vec<u8>)> {
     Ok((inputs, publics))
 }
 
-pub async fn remove_task_input(tid: &str) -> Result<()> {
-    let mut path = BASE_PATH.get().expect("Missing BASE PATH").clone();
-    path.push(tid);
-
-    fs::remove_file(path).await?;
-    Ok(())
-}
-
-pub async fn write_task_proof(tid: &str, proof: Vec<u8>) -> Result<()> {
-    let mut path = BASE_PATH.get().expect("Missing BASE PATH").clone();
-    path.push(format!("proof-{}", tid));
-
-    fs::write(path, proof).await?;
-    Ok(())
+/// the files of tasks in base path: inputs, proofs and samples
+fn is_task_file(name: &str) -> bool {
+    let name = name.strip_prefix("proof-").unwrap_or(name);
+    let numeric = !name.is_empty() && name.bytes().all(|b| b.is_ascii_digit());
+    numeric
+        || ["p-", "m-", "b-", "u-", "sample-"]
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
 }
 
-pub async fn read_task_proof(tid: &str) -> Result<Vec<u8>> {
-    let mut path = BASE_PATH.get().expect("Missing BASE PATH").clone();
-    path.push(format!("proof-{}", tid));
-
-    let bytes = fs::read(&path).await?;
-    fs::remove_file(path).await?;
+async fn prune_files(
+    dir: &Path,
+    retention: u64,
+    keep: &HashSet<String>,
+    api_proofs: bool,
+) -> Result<(usize, u64)> {
+    let before = SystemTime::now() - Duration::from_secs(retention);
+
+    let mut count = 0;
+    let mut bytes = 0;
+    let mut entries = fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let forced = api_proofs && name.starts_with("proof-p-");
+        if !is_task_file(&name) || (!forced && keep.contains(name.trim_start_matches("proof-"))) {
+            continue;
+        }
+        let meta = entry.metadata().await?;
+        if !meta.is_file() || meta.modified()? > before {
+            continue;
+        }
+        if fs::remove_file(entry.path()).await.is_ok() {
+            count += 1;
+            bytes += meta.len();
+        }
+    }
 
-    Ok(bytes)
+    Ok((count, bytes))
 }
 
-pub fn get_task_api(tid: &str) -> String {
-    let server = API_SERVER.get().expect("Missing API SERVER");
+/// the inner API of task in the server
+pub fn get_task_api(server: &str, tid: &str) -> String {
     format!("{}/inner/tasks/{}", server, tid)
 }
 
@@ -172,6 +268,50 @@ mod tests {
         assert_eq!(is_valid_url("ftp://example.com", true), false);
     }
 
+    #[tokio::test]
+    async fn test_prune_files() {
+        let dir = std::env::temp_dir().join(format!("pozk-prune-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "1",
+            "proof-1",
+            "p-abc",
+            "proof-p-abc",
+            "p-def",
+            "proof-p-def",
+            "proof-m-2",
+            "sample-0x01",
+            "2",
+            "node.key",
+        ] {
+            std::fs::write(dir.join(name), [0u8; 4]).unwrap();
+        }
+
+        // nothing is expired
+        assert_eq!(
+            prune_files(&dir, 3600, &HashSet::new(), false)
+                .await
+                .unwrap(),
+            (0, 0)
+        );
+
+        let keep = HashSet::from(["2".to_owned(), "sample-0x01".to_owned(), "p-def".to_owned()]);
+        assert_eq!(prune_files(&dir, 0, &keep, false).await.unwrap(), (5, 20));
+        assert!(dir.join("2").exists());
+        assert!(dir.join("sample-0x01").exists());
+        assert!(dir.join("node.key").exists());
+        assert!(dir.join("proof-p-def").exists());
+        assert!(!dir.join("proof-p-abc").exists());
+        assert!(!dir.join("proof-m-2").exists());
+
+        // over quota, the proofs of API tasks are removed even kept
+        assert_eq!(prune_files(&dir, 0, &keep, true).await.unwrap(), (1, 4));
+        assert!(dir.join("p-def").exists());
+        assert!(!dir.join("proof-p-def").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
     #[tokio::test]
     async fn test_download() {
         let res = download_input_with_uri("http://localhost:9098/inner/tasks/1")
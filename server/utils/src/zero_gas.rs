@@ -0,0 +1,71 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::{prelude::*, types::transaction::eip2718::TypedTransaction};
+use std::sync::Arc;
+
+use crate::networks::{create_zero_gas, zero_gas, zero_gas_balance, AAWallet, DefaultProvider};
+
+/// The 0 gas service which send txs by the AA wallet of controller
+#[async_trait]
+pub trait ZeroGasClient: Send + Sync {
+    /// the remaining allowance of controller, zero means no permission
+    async fn balance(&self, controller: Address) -> Result<U256>;
+
+    /// create or fetch the AA wallet of controller
+    async fn create(&self, controller: Address) -> Result<Address>;
+
+    /// the current nonce of AA wallet
+    async fn nonce(&self, wallet: Address) -> Result<u64>;
+
+    /// send tx by AA wallet, return tx hash, None if rejected by chain
+    async fn send(
+        &self,
+        tx: TypedTransaction,
+        chain: u64,
+        wallet: Address,
+        nonce: u64,
+        owner: &LocalWallet,
+    ) -> Result<Option<String>>;
+}
+
+/// 0 gas service by HTTP API, nonce read from the AA wallet contract
+pub struct HttpZeroGas {
+    uri: String,
+    provider: Arc<DefaultProvider>,
+}
+
+impl HttpZeroGas {
+    pub fn new(uri: &str, provider: Arc<DefaultProvider>) -> Self {
+        Self {
+            uri: uri.to_owned(),
+            provider,
+        }
+    }
+}
+
+#[async_trait]
+impl ZeroGasClient for HttpZeroGas {
+    async fn balance(&self, controller: Address) -> Result<U256> {
+        zero_gas_balance(&self.uri, controller).await
+    }
+
+    async fn create(&self, controller: Address) -> Result<Address> {
+        create_zero_gas(&self.uri, controller).await
+    }
+
+    async fn nonce(&self, wallet: Address) -> Result<u64> {
+        let aa = AAWallet::new(wallet, self.provider.clone());
+        Ok(aa.nonce().await?.as_u64())
+    }
+
+    async fn send(
+        &self,
+        tx: TypedTransaction,
+        chain: u64,
+        wallet: Address,
+        nonce: u64,
+        owner: &LocalWallet,
+    ) -> Result<Option<String>> {
+        zero_gas(&self.uri, tx, chain, wallet, nonce, owner).await
+    }
+}